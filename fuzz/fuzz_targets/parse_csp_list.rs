@@ -0,0 +1,11 @@
+#![no_main]
+use content_security_policy::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let csp_list = CspList::parse(data, PolicySource::Header, PolicyDisposition::Enforce);
+    let _ = csp_list.is_valid();
+    let serialized = csp_list.to_string();
+    let reparsed = CspList::parse(&serialized, PolicySource::Header, PolicyDisposition::Enforce);
+    assert_eq!(serialized, reparsed.to_string());
+});
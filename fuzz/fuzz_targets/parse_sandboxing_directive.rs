@@ -0,0 +1,10 @@
+#![no_main]
+use content_security_policy::sandboxing_directive::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let tokens: Vec<String> = data.split_ascii_whitespace().map(String::from).collect();
+    let flags = parse_a_sandboxing_directive(&tokens);
+    let serialized: Vec<String> = flags.to_tokens().iter().map(|t| t.to_string()).collect();
+    assert_eq!(parse_a_sandboxing_directive(&serialized), flags);
+});
@@ -0,0 +1,7 @@
+#![no_main]
+use content_security_policy::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &str| {
+    let _ = parse_subresource_integrity_metadata(data);
+});
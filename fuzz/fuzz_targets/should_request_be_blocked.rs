@@ -0,0 +1,71 @@
+#![no_main]
+use content_security_policy::*;
+use libfuzzer_sys::fuzz_target;
+
+const DESTINATIONS: &[Destination] = &[
+    Destination::None,
+    Destination::Audio,
+    Destination::AudioWorklet,
+    Destination::Document,
+    Destination::Embed,
+    Destination::FencedFrame,
+    Destination::Font,
+    Destination::Frame,
+    Destination::IFrame,
+    Destination::Image,
+    Destination::Json,
+    Destination::Manifest,
+    Destination::Object,
+    Destination::PaintWorklet,
+    Destination::Report,
+    Destination::Script,
+    Destination::ServiceWorker,
+    Destination::SharedWorker,
+    Destination::SpeculationRules,
+    Destination::Style,
+    Destination::Track,
+    Destination::Video,
+    Destination::WebIdentity,
+    Destination::Worker,
+    Destination::Xslt,
+];
+
+// The input is a policy, a URL and an origin URL, separated by newlines.
+// The first byte picks the destination.
+fuzz_target!(|data: &[u8]| {
+    let (&selector, data) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    let data = match std::str::from_utf8(data) {
+        Ok(data) => data,
+        Err(_) => return,
+    };
+    let mut lines = data.splitn(3, '\n');
+    let (policy, url, origin) = match (lines.next(), lines.next(), lines.next()) {
+        (Some(policy), Some(url), Some(origin)) => (policy, url, origin),
+        _ => return,
+    };
+    let (url, origin) = match (Url::parse(url), Url::parse(origin)) {
+        (Ok(url), Ok(origin)) => (url, origin.origin()),
+        _ => return,
+    };
+    let csp_list = CspList::parse(policy, PolicySource::Header, PolicyDisposition::Enforce);
+    let request = Request {
+        url: url.clone(),
+        origin,
+        redirect_count: u32::from(selector >> 7),
+        destination: DESTINATIONS[usize::from(selector) % DESTINATIONS.len()],
+        initiator: Initiator::None,
+        nonce: String::new(),
+        integrity_metadata: String::new(),
+        parser_metadata: ParserMetadata::ParserInserted,
+    };
+    let _ = csp_list.should_request_be_blocked(&request);
+    let response = Response {
+        csp_list: csp_list.clone(),
+        url,
+        redirect_count: request.redirect_count,
+    };
+    let _ = csp_list.should_response_to_request_be_blocked(&request, &response);
+});
@@ -0,0 +1,49 @@
+/*!
+Lists the requests in a HAR file that a policy would block.
+
+    csp-har-replay <policy> <file.har>
+
+Each blocked request is printed on its own line, as the check that blocked it, the
+effective directive, the violated directive, and the URL, separated by tabs.
+The exit status is 1 if any request would be blocked, and 2 on error.
+*/
+
+extern crate content_security_policy;
+
+use content_security_policy::har::{self, BlockedStage};
+use content_security_policy::{CspList, PolicyDisposition, PolicySource};
+use std::env;
+use std::fs;
+use std::process;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 {
+        eprintln!("usage: csp-har-replay <policy> <file.har>");
+        process::exit(2);
+    }
+    let csp_list = CspList::parse(&args[0], PolicySource::Header, PolicyDisposition::Enforce);
+    let recorded = fs::read_to_string(&args[1])
+        .map_err(|e| e.to_string())
+        .and_then(|har| har::parse_har(&har).map_err(|e| e.to_string()));
+    let recorded = match recorded {
+        Ok(recorded) => recorded,
+        Err(error) => {
+            eprintln!("csp-har-replay: {}: {}", args[1], error);
+            process::exit(2);
+        }
+    };
+    let blocked = har::replay(&recorded, &csp_list);
+    for blocked in &blocked {
+        let stage = match blocked.stage {
+            BlockedStage::Request => "request",
+            BlockedStage::Response => "response",
+        };
+        for violation in &blocked.violations {
+            println!("{}\t{}\t{}\t{}", stage, violation.effective_directive, violation.directive.to_string().trim_end(), blocked.request.url);
+        }
+    }
+    if !blocked.is_empty() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,97 @@
+/*!
+Which parts of CSP a user agent implements, so that a policy can be checked the way an
+older browser would check it.
+
+Browsers that do not understand a source expression or a directive ignore it.
+For example, a browser without `'strict-dynamic'` falls back to the allowlist and to
+`'unsafe-inline'`, and one without `script-src-elem` checks scripts against `script-src`.
+Checking a page with each of `BrowserProfile::CSP1`, `CSP2` and `CSP3` shows whether its
+policy still lets the page work, and still protects it, in those browsers.
+
+```rust
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::browser_profile::BrowserProfile;
+fn main() {
+    let csp_list = CspList::parse("script-src 'nonce-abc' 'unsafe-inline'", PolicySource::Header, PolicyDisposition::Enforce);
+    let element = Element { nonce: None };
+    let csp1_options = RequestCheckOptions::default().browser_profile(BrowserProfile::CSP1);
+    let (csp3, _) = csp_list.should_elements_inline_type_behavior_be_blocked(&element, InlineCheckType::Script, "alert(1)");
+    let (csp1, _) = csp_list.should_elements_inline_type_behavior_be_blocked_with_options(&element, InlineCheckType::Script, "alert(1)", &csp1_options);
+    assert_eq!(csp3, CheckResult::Blocked);
+    assert_eq!(csp1, CheckResult::Allowed);
+}
+```
+
+This crate does not check `eval()`, WebAssembly compilation or report delivery, so profiles
+say nothing about `'unsafe-eval'`, `'wasm-unsafe-eval'`, `report-uri` or `report-to`.
+*/
+
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+/// The CSP features that a user agent supports.
+///
+/// The default is `BrowserProfile::CSP3`, which is what the rest of this crate implements.
+///
+/// More features may be added, so a profile cannot be built field by field.
+/// Start from `BrowserProfile::CSP1`, `CSP2` or `CSP3`, and change the fields of a copy.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[non_exhaustive]
+pub struct BrowserProfile {
+    /// Nonce-sources and hash-sources, which also stop `'unsafe-inline'` from applying.
+    pub nonces_and_hashes: bool,
+    /// Hash-sources that match scripts loaded from URLs, through their integrity metadata.
+    pub external_script_hashes: bool,
+    /// The `'strict-dynamic'` keyword.
+    pub strict_dynamic: bool,
+    /// The `'unsafe-hashes'` keyword, which lets hash-sources match event handlers and
+    /// style attributes.
+    pub unsafe_hashes: bool,
+    /// The `script-src-elem`, `script-src-attr`, `style-src-elem` and `style-src-attr` directives.
+    pub element_and_attribute_directives: bool,
+    /// The `child-src` directive. Without it, frames fall back to `default-src`, and
+    /// workers to `script-src`.
+    pub child_src: bool,
+    /// The `worker-src` directive. Without it, workers fall back to `child-src`.
+    pub worker_src: bool,
+}
+
+impl BrowserProfile {
+    /// https://www.w3.org/TR/2012/CR-CSP-20121115/
+    pub const CSP1: BrowserProfile = BrowserProfile {
+        nonces_and_hashes: false,
+        external_script_hashes: false,
+        strict_dynamic: false,
+        unsafe_hashes: false,
+        element_and_attribute_directives: false,
+        child_src: false,
+        worker_src: false,
+    };
+    /// https://www.w3.org/TR/CSP2/
+    pub const CSP2: BrowserProfile = BrowserProfile {
+        nonces_and_hashes: true,
+        external_script_hashes: false,
+        strict_dynamic: false,
+        unsafe_hashes: false,
+        element_and_attribute_directives: false,
+        child_src: true,
+        worker_src: false,
+    };
+    /// https://www.w3.org/TR/CSP3/
+    pub const CSP3: BrowserProfile = BrowserProfile {
+        nonces_and_hashes: true,
+        external_script_hashes: true,
+        strict_dynamic: true,
+        unsafe_hashes: true,
+        element_and_attribute_directives: true,
+        child_src: true,
+        worker_src: true,
+    };
+}
+
+impl Default for BrowserProfile {
+    fn default() -> BrowserProfile {
+        BrowserProfile::CSP3
+    }
+}
@@ -0,0 +1,183 @@
+/*!
+Finding out how a policy's script protection degrades in browsers that only implement
+older levels of CSP.
+
+Strict policies rely on older browsers ignoring what they don't understand. For example,
+`script-src 'nonce-abc' 'strict-dynamic' 'unsafe-inline' https:` is strict in CSP3, falls
+back to nonces in CSP2, and allows any script in CSP1. That is a deliberate trade-off,
+but the same tricks can also leave a policy wide open, or break the page, by accident.
+
+`analyze` checks the policy's script directive with each `BrowserProfile`, and reports
+every level where inline scripts or scripts from any host are allowed when they aren't in
+CSP3, and every level where no script could run at all.
+
+```rust
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::compatibility::{self, CspLevel, IssueKind};
+fn main() {
+    let policy = Policy::parse("script-src 'nonce-abc' 'unsafe-inline'", PolicySource::Header, PolicyDisposition::Enforce);
+    let report = compatibility::analyze(&policy);
+    assert_eq!(report.issues.len(), 1);
+    assert_eq!(report.issues[0].level, CspLevel::Csp1);
+    assert_eq!(report.issues[0].kind, IssueKind::InlineScriptsAllowed);
+}
+```
+*/
+
+use browser_profile::BrowserProfile;
+use text_util::ascii_case_insensitive_match;
+use AllowResult;
+use InlineCheckType;
+use Policy;
+use SourceList;
+use get_fetch_directive_fallback_list;
+use get_the_effective_directive_for_inline_checks;
+use HASH_SOURCE_GRAMMAR;
+use HOST_SOURCE_GRAMMAR;
+use NONCE_SOURCE_GRAMMAR;
+use SCHEME_SOURCE_GRAMMAR;
+
+/// A level of CSP that browsers have implemented.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CspLevel {
+    Csp1,
+    Csp2,
+    Csp3,
+}
+
+impl CspLevel {
+    /// All levels, oldest first.
+    pub const ALL: [CspLevel; 3] = [CspLevel::Csp1, CspLevel::Csp2, CspLevel::Csp3];
+    pub fn profile(self) -> BrowserProfile {
+        match self {
+            CspLevel::Csp1 => BrowserProfile::CSP1,
+            CspLevel::Csp2 => BrowserProfile::CSP2,
+            CspLevel::Csp3 => BrowserProfile::CSP3,
+        }
+    }
+}
+
+/// What a policy lets scripts do in a browser that implements one level of CSP.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct LevelReport {
+    pub level: CspLevel,
+    /// The directive that governs scripts at this level, or `None` if scripts are not restricted.
+    pub directive: Option<&'static str>,
+    /// Whether every inline script is allowed.
+    pub inline_scripts: bool,
+    /// Whether scripts from any host are allowed, through `*` or a scheme-source like `https:`.
+    pub scripts_from_any_host: bool,
+    /// Whether any script at all could be allowed, inline or external.
+    pub scripts_can_run: bool,
+}
+
+impl LevelReport {
+    /// Whether the policy protects against injected scripts at this level.
+    pub fn is_protected(&self) -> bool {
+        !self.inline_scripts && !self.scripts_from_any_host
+    }
+}
+
+/// A way that a policy is weaker, or breaks more, at an older level than at CSP3.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum IssueKind {
+    /// All inline scripts are allowed, but they aren't in CSP3.
+    /// This happens when `'unsafe-inline'` is only disabled by nonces, hashes or `'strict-dynamic'`.
+    InlineScriptsAllowed,
+    /// Scripts from any host are allowed, but they aren't in CSP3.
+    /// This happens when `*` or a scheme-source is only disabled by `'strict-dynamic'`.
+    ScriptsFromAnyHostAllowed,
+    /// No script can run, but some can in CSP3.
+    /// This happens when scripts are only allowed by nonces, hashes or `'strict-dynamic'`,
+    /// with no host-source or `'unsafe-inline'` fallback.
+    ScriptsBlocked,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct CompatibilityIssue {
+    pub level: CspLevel,
+    pub kind: IssueKind,
+}
+
+/// The result of `analyze`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CompatibilityReport {
+    /// One report for each of `CspLevel::ALL`, in the same order.
+    pub levels: Vec<LevelReport>,
+    /// The issues at each level older than CSP3, oldest first.
+    pub issues: Vec<CompatibilityIssue>,
+}
+
+impl CompatibilityReport {
+    pub fn level(&self, level: CspLevel) -> &LevelReport {
+        self.levels.iter().find(|report| report.level == level).expect("every level is analyzed")
+    }
+}
+
+/// Analyzes how `policy` restricts scripts at each level of CSP.
+pub fn analyze(policy: &Policy) -> CompatibilityReport {
+    let levels: Vec<LevelReport> = CspLevel::ALL.iter().map(|&level| analyze_level(policy, level)).collect();
+    let csp3 = *levels.last().expect("CSP3 is analyzed");
+    let mut issues = Vec::new();
+    for report in &levels[..levels.len() - 1] {
+        let mut issue = |kind| issues.push(CompatibilityIssue { level: report.level, kind });
+        if report.inline_scripts && !csp3.inline_scripts {
+            issue(IssueKind::InlineScriptsAllowed);
+        }
+        if report.scripts_from_any_host && !csp3.scripts_from_any_host {
+            issue(IssueKind::ScriptsFromAnyHostAllowed);
+        }
+        if !report.scripts_can_run && csp3.scripts_can_run {
+            issue(IssueKind::ScriptsBlocked);
+        }
+    }
+    CompatibilityReport { levels, issues }
+}
+
+fn analyze_level(policy: &Policy, level: CspLevel) -> LevelReport {
+    let profile = level.profile();
+    let effective_directive = get_the_effective_directive_for_inline_checks(InlineCheckType::Script);
+    let name = get_fetch_directive_fallback_list(effective_directive, &profile).iter()
+        .cloned()
+        .find(|name| policy.contains_a_directive_whose_name_is(name));
+    let directive = name.and_then(|name| policy.directive_set.iter().find(|d| d.name == name));
+    let directive = match directive {
+        Some(directive) => directive,
+        None => return LevelReport {
+            level,
+            directive: None,
+            inline_scripts: true,
+            scripts_from_any_host: true,
+            scripts_can_run: true,
+        },
+    };
+    let source_list = SourceList(&directive.value);
+    let inline_scripts = source_list.does_a_source_list_allow_all_inline_behavior_for_type(InlineCheckType::Script, &profile) == AllowResult::Allows;
+    // `'strict-dynamic'` makes the browser ignore the allowlist.
+    let allowlist_is_used = !(profile.strict_dynamic && directive.value.iter().any(|ex| ascii_case_insensitive_match(ex, "'strict-dynamic'")));
+    let mut scripts_from_any_host = false;
+    let mut allowlist_is_empty = true;
+    for expression in &directive.value {
+        if SCHEME_SOURCE_GRAMMAR.is_match(expression) {
+            scripts_from_any_host = true;
+            allowlist_is_empty = false;
+        } else if let Some(captures) = HOST_SOURCE_GRAMMAR.captures(expression) {
+            if captures.name("host").map(|host| host.as_str() == "*").unwrap_or(false) {
+                scripts_from_any_host = true;
+            }
+            allowlist_is_empty = false;
+        } else if ascii_case_insensitive_match(expression, "'self'") {
+            allowlist_is_empty = false;
+        }
+    }
+    let has_nonces_or_hashes = profile.nonces_and_hashes && directive.value.iter()
+        .any(|ex| NONCE_SOURCE_GRAMMAR.is_match(ex) || HASH_SOURCE_GRAMMAR.is_match(ex));
+    LevelReport {
+        level,
+        directive: name,
+        inline_scripts,
+        scripts_from_any_host: allowlist_is_used && scripts_from_any_host,
+        scripts_can_run: inline_scripts || has_nonces_or_hashes || (allowlist_is_used && !allowlist_is_empty),
+    }
+}
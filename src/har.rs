@@ -0,0 +1,312 @@
+/*!
+Replaying the requests recorded in a [HAR] file against a candidate policy, to find out
+which of them the policy would block.
+
+This module is only available with the `har` feature. The `csp-har-replay` binary is a
+command-line front end for it.
+
+A HAR file does not record everything that a `Request` needs, so the replayed requests
+are approximations:
+
+* The destination comes from the `_resourceType` field that Chromium adds to each entry,
+  or from the response's MIME type when that field is missing. Documents after the first
+  one on a page are assumed to be iframes.
+* The origin is the origin of the page the entry belongs to, or of the first entry if
+  there are no pages.
+* Redirects are followed through each response's `redirectURL`, so the request it leads
+  to gets the right `redirect_count`.
+* Nonces, integrity metadata and parser metadata are not recorded, so they are left empty.
+  Requests that a policy only allows because of a nonce or a hash will be reported as blocked.
+
+[HAR]: https://w3c.github.io/web-performance/specs/HAR/Overview.html
+*/
+
+use serde::Deserialize;
+use serde_json;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use url::{self, Origin, Url};
+use CheckResult;
+use CspList;
+use Destination;
+use Initiator;
+use ParserMetadata;
+use PolicyDisposition;
+use PolicySource;
+use Request;
+use Response;
+use Violation;
+
+#[derive(Deserialize)]
+struct Har {
+    log: HarLog,
+}
+
+#[derive(Deserialize)]
+struct HarLog {
+    #[serde(default)]
+    pages: Vec<HarPage>,
+    entries: Vec<HarEntry>,
+}
+
+#[derive(Deserialize)]
+struct HarPage {
+    id: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct HarEntry {
+    pageref: Option<String>,
+    request: HarRequest,
+    response: HarResponse,
+    #[serde(rename = "_resourceType")]
+    resource_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct HarRequest {
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct HarResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<HarHeader>,
+    #[serde(default)]
+    content: HarContent,
+    #[serde(default, rename = "redirectURL")]
+    redirect_url: String,
+}
+
+#[derive(Deserialize)]
+struct HarHeader {
+    name: String,
+    value: String,
+}
+
+#[derive(Default, Deserialize)]
+struct HarContent {
+    #[serde(default, rename = "mimeType")]
+    mime_type: String,
+}
+
+/// An error reading a HAR file.
+#[derive(Debug)]
+pub enum HarError {
+    /// The file is not valid JSON, or is missing a required HAR field.
+    Json(serde_json::Error),
+    /// An entry's URL could not be parsed.
+    Url(String, url::ParseError),
+}
+
+impl fmt::Display for HarError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            HarError::Json(ref error) => write!(f, "invalid HAR file: {}", error),
+            HarError::Url(ref url, ref error) => write!(f, "invalid URL {:?} in HAR file: {}", url, error),
+        }
+    }
+}
+
+impl Error for HarError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            HarError::Json(ref error) => Some(error),
+            HarError::Url(_, ref error) => Some(error),
+        }
+    }
+}
+
+impl From<serde_json::Error> for HarError {
+    fn from(error: serde_json::Error) -> HarError {
+        HarError::Json(error)
+    }
+}
+
+/// A request read from a HAR file, with the response it received.
+#[derive(Clone, Debug)]
+pub struct RecordedRequest {
+    pub request: Request,
+    /// The response, with its `csp_list` taken from the recorded response headers.
+    pub response: Response,
+}
+
+/// Which check blocked a `BlockedRequest`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BlockedStage {
+    /// `CspList::should_request_be_blocked`
+    Request,
+    /// `CspList::should_response_to_request_be_blocked`
+    Response,
+}
+
+/// A recorded request that a policy would block.
+#[derive(Clone, Debug)]
+pub struct BlockedRequest {
+    pub request: Request,
+    pub stage: BlockedStage,
+    /// The violations reported by the check that blocked the request.
+    ///
+    /// Each one names the responsible directive.
+    pub violations: Vec<Violation>,
+}
+
+/// Reads the requests recorded in a HAR file, in the order they appear in it.
+pub fn parse_har(har: &str) -> Result<Vec<RecordedRequest>, HarError> {
+    let har: Har = serde_json::from_str(har)?;
+    let parse_url = |url: &str| Url::parse(url).map_err(|e| HarError::Url(url.to_owned(), e));
+    let mut page_origins = HashMap::new();
+    for page in &har.log.pages {
+        if let Ok(url) = Url::parse(&page.title) {
+            page_origins.insert(&page.id[..], url.origin());
+        }
+    }
+    let default_origin = match har.log.entries.first() {
+        Some(entry) => parse_url(&entry.request.url)?.origin(),
+        None => return Ok(Vec::new()),
+    };
+    // Requests that a redirect leads to, with the redirect count and destination they inherit.
+    let mut redirects: HashMap<Url, (u32, Destination)> = HashMap::new();
+    let mut pages_seen = HashSet::new();
+    let mut recorded = Vec::with_capacity(har.log.entries.len());
+    for entry in &har.log.entries {
+        let url = parse_url(&entry.request.url)?;
+        let origin = entry.pageref.as_ref()
+            .and_then(|pageref| page_origins.get(&pageref[..]))
+            .unwrap_or(&default_origin)
+            .clone();
+        let (redirect_count, destination) = match redirects.remove(&url) {
+            Some(redirect) => redirect,
+            // Chromium records frames as documents, too. Only the first one on a page is the page itself.
+            None => match destination_for_entry(entry) {
+                Destination::Document if !pages_seen.insert(entry.pageref.clone()) => (0, Destination::IFrame),
+                destination => (0, destination),
+            },
+        };
+        pages_seen.insert(entry.pageref.clone());
+        if (300..400).contains(&entry.response.status) && !entry.response.redirect_url.is_empty() {
+            if let Ok(location) = url.join(&entry.response.redirect_url) {
+                redirects.insert(location, (redirect_count + 1, destination));
+            }
+        }
+        recorded.push(RecordedRequest {
+            request: request(url.clone(), origin, redirect_count, destination),
+            response: Response {
+                csp_list: csp_list_from_headers(&entry.response.headers),
+                url,
+                redirect_count,
+            },
+        });
+    }
+    Ok(recorded)
+}
+
+/**
+Runs each recorded request through `csp_list`, and returns the ones that would be blocked.
+
+Only the request and post-request checks of `csp_list` are run. The policies that each
+recorded response set for itself, like `frame-ancestors` or `sandbox`, were already there
+when it was recorded, so they are not what is being tried out.
+A request that is blocked before it is sent is not also checked against its response.
+
+Each page's top-level navigation, including the redirects that lead to it, is left out:
+it is the response that delivers the policy, so the policy cannot block it.
+*/
+pub fn replay(recorded: &[RecordedRequest], csp_list: &CspList) -> Vec<BlockedRequest> {
+    let mut blocked = Vec::new();
+    for recorded in recorded {
+        if recorded.request.destination == Destination::Document {
+            continue;
+        }
+        let (result, violations) = csp_list.should_request_be_blocked(&recorded.request);
+        if result == CheckResult::Blocked {
+            blocked.push(BlockedRequest {
+                request: recorded.request.clone(),
+                stage: BlockedStage::Request,
+                violations,
+            });
+            continue;
+        }
+        let response = Response {
+            csp_list: CspList::default(),
+            url: recorded.response.url.clone(),
+            redirect_count: recorded.response.redirect_count,
+        };
+        let (result, violations) = csp_list.should_response_to_request_be_blocked(&recorded.request, &response);
+        if result == CheckResult::Blocked {
+            blocked.push(BlockedRequest {
+                request: recorded.request.clone(),
+                stage: BlockedStage::Response,
+                violations: violations.into_iter()
+                    .filter(|violation| violation.disposition == PolicyDisposition::Enforce)
+                    .collect(),
+            });
+        }
+    }
+    blocked
+}
+
+fn request(url: Url, origin: Origin, redirect_count: u32, destination: Destination) -> Request {
+    Request {
+        url,
+        origin,
+        redirect_count,
+        destination,
+        initiator: Initiator::None,
+        nonce: String::new(),
+        integrity_metadata: String::new(),
+        parser_metadata: ParserMetadata::None,
+    }
+}
+
+fn csp_list_from_headers(headers: &[HarHeader]) -> CspList {
+    let mut csp_list = CspList::default();
+    for header in headers {
+        let disposition = if header.name.eq_ignore_ascii_case("content-security-policy") {
+            PolicyDisposition::Enforce
+        } else if header.name.eq_ignore_ascii_case("content-security-policy-report-only") {
+            PolicyDisposition::Report
+        } else {
+            continue;
+        };
+        csp_list.append(CspList::parse(&header.value, PolicySource::Header, disposition));
+    }
+    csp_list
+}
+
+fn destination_for_entry(entry: &HarEntry) -> Destination {
+    let mime_type = entry.response.content.mime_type.to_ascii_lowercase();
+    match entry.resource_type.as_ref().map(|t| &t[..]) {
+        Some("document") => Destination::Document,
+        Some("stylesheet") => Destination::Style,
+        Some("image") => Destination::Image,
+        Some("media") if mime_type.starts_with("audio/") => Destination::Audio,
+        Some("media") => Destination::Video,
+        Some("font") => Destination::Font,
+        Some("script") => Destination::Script,
+        Some("texttrack") => Destination::Track,
+        Some("manifest") => Destination::Manifest,
+        Some("csp_violation_report") => Destination::Report,
+        Some("xhr") | Some("fetch") | Some("eventsource") | Some("websocket") | Some("ping") => Destination::None,
+        _ => destination_for_mime_type(&mime_type),
+    }
+}
+
+fn destination_for_mime_type(mime_type: &str) -> Destination {
+    let essence = mime_type.split(';').next().unwrap_or("").trim();
+    match essence {
+        "text/html" | "application/xhtml+xml" => Destination::Document,
+        "text/css" => Destination::Style,
+        "text/javascript" | "application/javascript" | "application/x-javascript" => Destination::Script,
+        "text/vtt" => Destination::Track,
+        "application/manifest+json" => Destination::Manifest,
+        _ if essence.starts_with("image/") => Destination::Image,
+        _ if essence.starts_with("font/") => Destination::Font,
+        _ if essence.starts_with("audio/") => Destination::Audio,
+        _ if essence.starts_with("video/") => Destination::Video,
+        _ => Destination::None,
+    }
+}
@@ -0,0 +1,147 @@
+/*!
+Reading and writing `Content-Security-Policy` and `Content-Security-Policy-Report-Only`
+headers with the [`http`] and [`headers`] crates.
+
+This module is only available with the `http` feature.
+
+[`http`]: https://docs.rs/http
+[`headers`]: https://docs.rs/headers
+*/
+
+use headers::{self, Header};
+use http::header::{HeaderMap, HeaderName, HeaderValue, InvalidHeaderValue};
+use CspList;
+use PolicyDisposition;
+use PolicySource;
+
+// `Header::name` needs a `'static` reference, which the constants in `http::header` cannot provide.
+static CONTENT_SECURITY_POLICY: HeaderName =
+    http::header::CONTENT_SECURITY_POLICY;
+static CONTENT_SECURITY_POLICY_REPORT_ONLY: HeaderName =
+    http::header::CONTENT_SECURITY_POLICY_REPORT_ONLY;
+
+/// https://infra.spec.whatwg.org/#isomorphic-decode
+fn isomorphic_decode(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| char::from(b)).collect()
+}
+
+/// https://infra.spec.whatwg.org/#isomorphic-encode
+fn isomorphic_encode(string: &str) -> Option<Vec<u8>> {
+    string.chars()
+        .map(|c| if (c as u32) <= 0xFF { Some(c as u8) } else { None })
+        .collect()
+}
+
+fn parse_header_values<'a, I: IntoIterator<Item=&'a HeaderValue>>(values: I, disposition: PolicyDisposition) -> CspList {
+    let mut csp_list = CspList(Vec::new());
+    for value in values {
+        csp_list.append(CspList::parse(&isomorphic_decode(value.as_bytes()), PolicySource::Header, disposition));
+    }
+    csp_list
+}
+
+/// Policies that were parsed from headers are turned back into the same bytes.
+/// Other policies, which may contain characters past U+00FF, are sent as UTF-8.
+fn to_header_value(serialized: &str) -> Result<HeaderValue, InvalidHeaderValue> {
+    match isomorphic_encode(serialized) {
+        Some(bytes) => HeaderValue::from_bytes(&bytes),
+        None => HeaderValue::from_str(serialized),
+    }
+}
+
+impl CspList {
+    /**
+    Parse every `Content-Security-Policy` and `Content-Security-Policy-Report-Only` header
+    in the map, in that order.
+
+    Header values that are not valid UTF-8 are decoded byte-by-byte, as the spec requires.
+
+    https://www.w3.org/TR/CSP/#parse-response-csp
+    */
+    pub fn from_header_map(headers: &HeaderMap) -> CspList {
+        let mut csp_list = parse_header_values(headers.get_all(&CONTENT_SECURITY_POLICY), PolicyDisposition::Enforce);
+        csp_list.append(parse_header_values(headers.get_all(&CONTENT_SECURITY_POLICY_REPORT_ONLY), PolicyDisposition::Report));
+        csp_list
+    }
+    /**
+    Serialize every policy as its own header, named after the policy's disposition.
+
+    This fails if a policy contains a character that cannot be sent in a header,
+    such as a control character.
+    */
+    pub fn to_header_values(&self) -> Result<Vec<(HeaderName, HeaderValue)>, InvalidHeaderValue> {
+        let mut values = Vec::with_capacity(self.0.len());
+        for policy in &self.0 {
+            let name = match policy.disposition {
+                PolicyDisposition::Enforce => CONTENT_SECURITY_POLICY.clone(),
+                PolicyDisposition::Report => CONTENT_SECURITY_POLICY_REPORT_ONLY.clone(),
+            };
+            values.push((name, to_header_value(&policy.to_string())?));
+        }
+        Ok(values)
+    }
+    /**
+    Add every policy to the header map, as returned by `to_header_values`.
+
+    Headers already in the map are kept.
+    */
+    pub fn append_to_header_map(&self, headers: &mut HeaderMap) -> Result<(), InvalidHeaderValue> {
+        for (name, value) in self.to_header_values()? {
+            headers.append(name, value);
+        }
+        Ok(())
+    }
+}
+
+fn decode<'i, I: Iterator<Item=&'i HeaderValue>>(values: &mut I, disposition: PolicyDisposition) -> Result<CspList, headers::Error> {
+    let mut values = values.peekable();
+    if values.peek().is_none() {
+        return Err(headers::Error::invalid());
+    }
+    Ok(parse_header_values(values, disposition))
+}
+
+fn encode<E: Extend<HeaderValue>>(csp_list: &CspList, values: &mut E) {
+    // `Header::encode` cannot fail, so policies that cannot be sent in a header are left out.
+    values.extend(csp_list.0.iter().filter_map(|policy| to_header_value(&policy.to_string()).ok()));
+}
+
+/**
+The `Content-Security-Policy` header, as a typed header.
+
+Every policy in it should have the `Enforce` disposition.
+*/
+#[derive(Clone, Debug)]
+pub struct ContentSecurityPolicy(pub CspList);
+
+impl Header for ContentSecurityPolicy {
+    fn name() -> &'static HeaderName {
+        &CONTENT_SECURITY_POLICY
+    }
+    fn decode<'i, I: Iterator<Item=&'i HeaderValue>>(values: &mut I) -> Result<Self, headers::Error> {
+        decode(values, PolicyDisposition::Enforce).map(ContentSecurityPolicy)
+    }
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        encode(&self.0, values)
+    }
+}
+
+/**
+The `Content-Security-Policy-Report-Only` header, as a typed header.
+
+Every policy in it should have the `Report` disposition.
+*/
+#[derive(Clone, Debug)]
+pub struct ContentSecurityPolicyReportOnly(pub CspList);
+
+impl Header for ContentSecurityPolicyReportOnly {
+    fn name() -> &'static HeaderName {
+        &CONTENT_SECURITY_POLICY_REPORT_ONLY
+    }
+    fn decode<'i, I: Iterator<Item=&'i HeaderValue>>(values: &mut I) -> Result<Self, headers::Error> {
+        decode(values, PolicyDisposition::Report).map(ContentSecurityPolicyReportOnly)
+    }
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        encode(&self.0, values)
+    }
+}
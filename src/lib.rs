@@ -32,6 +32,15 @@ fn main() {
     assert_eq!(check_result, CheckResult::Blocked);
 }
 ```
+
+# Serde
+
+With the `serde` feature, requests, responses, elements and violations can be
+recorded as JSON and replayed later. The format is kept stable: structs become
+objects keyed by their field names, enums are externally tagged with their
+variant names, URLs are strings, origins are their ASCII serialization (opaque
+origins are `"null"`, and deserialize to a fresh opaque origin), and
+`SandboxingFlagSet` is the list of the names of its flags.
 */
 
 #![forbid(unsafe_code)]
@@ -46,15 +55,79 @@ extern crate lazy_static;
 extern crate bitflags;
 #[cfg(feature = "serde")]
 extern crate serde;
+extern crate sha2;
+extern crate base64;
+#[cfg(feature = "http")]
+pub extern crate http;
+#[cfg(feature = "http")]
+pub extern crate headers;
+#[cfg(feature = "tower")]
+extern crate tower_layer;
+#[cfg(feature = "tower")]
+extern crate tower_service;
+#[cfg(feature = "tower")]
+#[macro_use]
+extern crate pin_project_lite;
+#[cfg(feature = "tower")]
+extern crate getrandom;
+#[cfg(any(feature = "report-collector", feature = "har"))]
+extern crate serde_json;
+#[cfg(feature = "report-collector")]
+extern crate http_body;
+#[cfg(feature = "report-collector")]
+extern crate http_body_util;
 
 pub mod text_util;
 pub mod sandboxing_directive;
+pub mod policy_container;
+pub mod scheme_registry;
+pub mod browser_profile;
+pub mod compatibility;
+pub mod migration;
+#[cfg(feature = "http")]
+pub mod http_headers;
+#[cfg(feature = "tower")]
+pub mod middleware;
+#[cfg(feature = "report-collector")]
+pub mod report_collector;
+#[cfg(feature = "har")]
+pub mod har;
 
 pub use url::{Origin, Url};
+use url::Host;
+use browser_profile::BrowserProfile;
+use std::net::Ipv4Addr;
 #[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+/**
+Origins are serialized as their ASCII serialization, like `https://example.com:8443`,
+because the `url` crate does not implement serde for them.
+
+Opaque origins are serialized as `null`, and each one is deserialized as a new opaque origin.
+*/
+#[cfg(feature = "serde")]
+mod serde_origin {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde::de::Error;
+    use {Origin, Url};
+    pub fn serialize<S: Serializer>(origin: &Origin, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&origin.ascii_serialization())
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Origin, D::Error> {
+        let origin = String::deserialize(deserializer)?;
+        if origin == "null" {
+            return Ok(Origin::new_opaque());
+        }
+        Url::parse(&origin).map(|url| url.origin()).map_err(D::Error::custom)
+    }
+}
 use std::borrow::{Borrow, Cow};
 use std::fmt::{self, Display, Formatter};
+use std::convert::TryFrom;
+use std::error::Error;
+use std::str::FromStr;
 use text_util::{
+    is_char_ascii_whitespace,
     strip_leading_and_trailing_ascii_whitespace,
     split_ascii_whitespace,
     split_commas,
@@ -62,17 +135,11 @@ use text_util::{
     collect_a_sequence_of_non_ascii_white_space_code_points,
 };
 use sandboxing_directive::{SandboxingFlagSet, parse_a_sandboxing_directive};
+use scheme_registry::SchemeRegistry;
 use MatchResult::Matches;
 use MatchResult::DoesNotMatch;
 use std::collections::HashSet;
-
-fn scheme_is_network(scheme: &str) -> bool {
-    scheme == "ftp" || scheme_is_httpx(scheme)
-}
-
-fn scheme_is_httpx(scheme: &str) -> bool {
-    scheme == "http" || scheme == "https"
-}
+use std::sync::Arc;
 
 /**
 A single parsed content security policy.
@@ -107,6 +174,12 @@ impl Policy {
     }
     /// https://www.w3.org/TR/CSP/#parse-serialized-policy
     pub fn parse(serialized: &str, source: PolicySource, disposition: PolicyDisposition) -> Policy {
+        Policy::parse_with_diagnostics(serialized, source, disposition).0
+    }
+    /// Like `parse`, but also returns warnings about the policy.
+    pub fn parse_with_diagnostics(serialized: &str, source: PolicySource, disposition: PolicyDisposition)
+        -> (Policy, Vec<ParseDiagnostic>) {
+        let mut diagnostics = Vec::new();
         let mut policy = Policy {
             directive_set: Vec::new(),
             source, disposition,
@@ -123,21 +196,43 @@ impl Policy {
                 continue;
             }
             let directive_value = split_ascii_whitespace(token).map(String::from).collect();
+            if LEGACY_DIRECTIVE_NAMES.contains(&&directive_name[..]) {
+                diagnostics.push(ParseDiagnostic::DeprecatedDirective(directive_name.clone()));
+            }
             policy.directive_set.push(Directive {
                 name: directive_name,
                 value: directive_value,
             });
         }
-        policy
+        (policy, diagnostics)
+    }
+    /**
+    Parses a policy like `parse` does, but fails instead of skipping anything that isn't
+    valid [serialized CSP] syntax.
+
+    This is meant for servers that check their own configuration. Browsers should use `parse`.
+
+    [serialized CSP]: https://www.w3.org/TR/CSP/#grammardef-serialized-policy
+    */
+    pub fn parse_strict(serialized: &str, source: PolicySource, disposition: PolicyDisposition) -> Result<Policy, PolicyError> {
+        check_serialized_policy(serialized, 0)?;
+        Ok(Policy::parse(serialized, source, disposition))
     }
     pub fn contains_a_directive_whose_name_is(&self, directive_name: &str) -> bool {
         self.directive_set.iter().any(|d| d.name == directive_name)
     }
     /// https://www.w3.org/TR/CSP/#does-request-violate-policy
     pub fn does_request_violate_policy(&self, request: &Request) -> Violates {
+        self.does_request_violate_policy_with_options(request, &RequestCheckOptions::default())
+    }
+    /// Like `does_request_violate_policy`, but also runs the checks that `options` enables.
+    pub fn does_request_violate_policy_with_options(&self, request: &Request, options: &RequestCheckOptions) -> Violates {
         let mut violates = Violates::DoesNotViolate;
         for directive in &self.directive_set {
-            let result = directive.pre_request_check(request, self);
+            let mut result = directive.pre_request_check_with_options(request, self, options);
+            if options.require_sri_for && directive.name == "require-sri-for" {
+                result = directive.require_sri_for_check(request);
+            }
             if result == CheckResult::Blocked {
                 violates = Violates::Directive(directive.clone());
             }
@@ -146,7 +241,201 @@ impl Policy {
     }
 }
 
-#[derive(Clone, Debug)]
+/// Parses a header-delivered, enforced policy with `Policy::parse_strict`.
+impl FromStr for Policy {
+    type Err = PolicyError;
+    fn from_str(serialized: &str) -> Result<Policy, PolicyError> {
+        Policy::parse_strict(serialized, PolicySource::Header, PolicyDisposition::Enforce)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for Policy {
+    type Error = PolicyError;
+    fn try_from(serialized: &'a str) -> Result<Policy, PolicyError> {
+        serialized.parse()
+    }
+}
+
+/// The first problem that `Policy::parse_strict` or `CspList::parse_strict` found.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PolicyError {
+    pub kind: PolicyErrorKind,
+    /// The byte offset in the parsed string where the problem is.
+    pub position: usize,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PolicyErrorKind {
+    /// A policy has no directives.
+    Empty,
+    /// A directive name contains characters other than ASCII letters, digits and `-`.
+    InvalidDirectiveName(String),
+    /// A token in a directive's value contains whitespace, `,`, `;` or a non-ASCII character.
+    InvalidDirectiveValue { directive: String, token: String },
+    /// A directive appears more than once in a policy. `parse` ignores all but the first.
+    DuplicateDirective(String),
+}
+
+impl Display for PolicyError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self.kind {
+            PolicyErrorKind::Empty => write!(f, "empty policy")?,
+            PolicyErrorKind::InvalidDirectiveName(ref name) => write!(f, "invalid directive name {:?}", name)?,
+            PolicyErrorKind::InvalidDirectiveValue { ref directive, ref token } => write!(f, "invalid value {:?} for directive {}", token, directive)?,
+            PolicyErrorKind::DuplicateDirective(ref name) => write!(f, "duplicate directive {}", name)?,
+        }
+        write!(f, " at byte {}", self.position)
+    }
+}
+
+impl Error for PolicyError {}
+
+/// Checks the policy `serialized`, which starts at byte `offset` of the string being parsed,
+/// against the serialized-policy grammar.
+fn check_serialized_policy(serialized: &str, offset: usize) -> Result<(), PolicyError> {
+    let error = |kind, position| Err(PolicyError { kind, position });
+    let mut names = HashSet::new();
+    let mut token_start = offset;
+    for token in serialized.split(';') {
+        let mut position = token_start + token.len() - token.trim_start_matches(is_char_ascii_whitespace).len();
+        token_start += token.len() + 1;
+        let token = strip_leading_and_trailing_ascii_whitespace(token);
+        if token.is_empty() { continue };
+        let (directive_name, mut value) = collect_a_sequence_of_non_ascii_white_space_code_points(token);
+        let name = directive_name.to_ascii_lowercase();
+        if !DIRECTIVE_NAME_GRAMMAR.is_match(&name) {
+            return error(PolicyErrorKind::InvalidDirectiveName(directive_name.to_owned()), position);
+        }
+        if !names.insert(name.clone()) {
+            return error(PolicyErrorKind::DuplicateDirective(name), position);
+        }
+        position += directive_name.len();
+        loop {
+            let trimmed = value.trim_start_matches(is_char_ascii_whitespace);
+            position += value.len() - trimmed.len();
+            if trimmed.is_empty() { break };
+            let (value_token, rest) = collect_a_sequence_of_non_ascii_white_space_code_points(trimmed);
+            if !DIRECTIVE_VALUE_TOKEN_GRAMMAR.is_match(value_token) {
+                let kind = PolicyErrorKind::InvalidDirectiveValue { directive: name, token: value_token.to_owned() };
+                return error(kind, position);
+            }
+            position += value_token.len();
+            value = rest;
+        }
+    }
+    if names.is_empty() {
+        return error(PolicyErrorKind::Empty, offset);
+    }
+    Ok(())
+}
+
+/// Directives that browsers have dropped, or never shipped.
+const LEGACY_DIRECTIVE_NAMES: &[&str] = &[
+    "block-all-mixed-content",
+    "navigate-to",
+    "plugin-types",
+    "prefetch-src",
+    "referrer",
+    "reflected-xss",
+];
+
+/// How `RequestCheckOptions` treats the legacy directives `block-all-mixed-content` and
+/// `prefetch-src` when checking requests.
+///
+/// The other legacy directives are only used by the `CspList` methods that are named after
+/// them: `should_plugin_be_blocked` for `plugin-types`, `should_navigation_request_be_blocked`
+/// for `navigate-to`, and `legacy_referrer_policy` and `legacy_x_xss_protection` for
+/// `referrer` and `reflected-xss`.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum LegacyDirectives {
+    /// Keep them in the policy, but make them no-ops, as current browsers do.
+    #[default]
+    Ignore,
+    /// Give them the semantics that older browsers had:
+    ///
+    /// * `block-all-mixed-content` blocks requests for insecure URLs from secure origins.
+    /// * `prefetch-src` checks prefetch and prerender requests instead of `default-src`.
+    Enforce,
+}
+
+/// A warning about a parsed policy.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum ParseDiagnostic {
+    /// The policy contains a legacy directive, which current browsers ignore.
+    /// See `LegacyDirectives` for how this crate can still use it.
+    DeprecatedDirective(String),
+}
+
+/**
+Options for the `*_with_options` checks of `CspList`: checks that they can run in addition
+to the ones in the spec, and the browser whose behaviour they simulate.
+
+More options may be added, so build them from `RequestCheckOptions::default()`:
+
+```rust
+extern crate content_security_policy;
+use content_security_policy::RequestCheckOptions;
+use content_security_policy::browser_profile::BrowserProfile;
+fn main() {
+    let options = RequestCheckOptions::default()
+        .require_sri_for(true)
+        .browser_profile(BrowserProfile::CSP2);
+    assert!(options.require_sri_for);
+}
+```
+*/
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[non_exhaustive]
+pub struct RequestCheckOptions {
+    /// Enforce the `require-sri-for` directive, which was drafted but never fully shipped
+    /// in browsers. See `Directive::require_sri_for_check`.
+    pub require_sri_for: bool,
+    /// Only use the CSP features that this profile supports.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub browser_profile: BrowserProfile,
+    /// The URL schemes that source expressions are matched with.
+    /// The default only contains the schemes that the spec knows about.
+    #[cfg_attr(feature = "serde", serde(skip, default = "scheme_registry::shared_default"))]
+    pub scheme_registry: Arc<SchemeRegistry>,
+    /// Whether `block-all-mixed-content` and `prefetch-src` are enforced.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub legacy_directives: LegacyDirectives,
+}
+
+impl Default for RequestCheckOptions {
+    fn default() -> RequestCheckOptions {
+        RequestCheckOptions {
+            require_sri_for: false,
+            browser_profile: BrowserProfile::default(),
+            scheme_registry: scheme_registry::shared_default(),
+            legacy_directives: LegacyDirectives::default(),
+        }
+    }
+}
+
+impl RequestCheckOptions {
+    pub fn require_sri_for(mut self, require_sri_for: bool) -> RequestCheckOptions {
+        self.require_sri_for = require_sri_for;
+        self
+    }
+    pub fn browser_profile(mut self, browser_profile: BrowserProfile) -> RequestCheckOptions {
+        self.browser_profile = browser_profile;
+        self
+    }
+    pub fn scheme_registry<R: Into<Arc<SchemeRegistry>>>(mut self, scheme_registry: R) -> RequestCheckOptions {
+        self.scheme_registry = scheme_registry.into();
+        self
+    }
+    pub fn legacy_directives(mut self, legacy_directives: LegacyDirectives) -> RequestCheckOptions {
+        self.legacy_directives = legacy_directives;
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 /// https://www.w3.org/TR/CSP/#csp-list
 pub struct CspList(pub Vec<Policy>);
@@ -163,6 +452,27 @@ impl Display for CspList {
     }
 }
 
+/// Parses header-delivered, enforced policies with `CspList::parse_strict`.
+impl FromStr for CspList {
+    type Err = PolicyError;
+    fn from_str(list: &str) -> Result<CspList, PolicyError> {
+        CspList::parse_strict(list, PolicySource::Header, PolicyDisposition::Enforce)
+    }
+}
+
+impl<'a> TryFrom<&'a str> for CspList {
+    type Error = PolicyError;
+    fn try_from(list: &'a str) -> Result<CspList, PolicyError> {
+        list.parse()
+    }
+}
+
+impl From<Policy> for CspList {
+    fn from(policy: Policy) -> CspList {
+        CspList(vec![policy])
+    }
+}
+
 impl CspList {
     pub fn is_valid(&self) -> bool {
         self.0.iter().all(Policy::is_valid)
@@ -181,6 +491,30 @@ impl CspList {
         }
         CspList(policies)
     }
+    /// Like `parse`, but also returns warnings about the policies.
+    pub fn parse_with_diagnostics(list: &str, source: PolicySource, disposition: PolicyDisposition)
+        -> (CspList, Vec<ParseDiagnostic>) {
+        let mut policies = Vec::new();
+        let mut diagnostics = Vec::new();
+        for token in split_commas(list) {
+            let (policy, mut policy_diagnostics) = Policy::parse_with_diagnostics(token, source, disposition);
+            diagnostics.append(&mut policy_diagnostics);
+            if policy.directive_set.is_empty() { continue };
+            policies.push(policy)
+        }
+        (CspList(policies), diagnostics)
+    }
+    /// Parses a list like `parse` does, but fails if any of its policies fails `Policy::parse_strict`.
+    ///
+    /// Empty lists, and empty policies between commas, are errors too.
+    pub fn parse_strict(list: &str, source: PolicySource, disposition: PolicyDisposition) -> Result<CspList, PolicyError> {
+        let mut token_start = 0;
+        for token in list.split(',') {
+            check_serialized_policy(token, token_start)?;
+            token_start += token.len() + 1;
+        }
+        Ok(CspList::parse(list, source, disposition))
+    }
     pub fn append(&mut self, mut other: CspList) {
         self.0.append(&mut other.0)
     }
@@ -193,14 +527,18 @@ impl CspList {
     https://www.w3.org/TR/CSP/#report-for-request
     */
     pub fn report_violations_for_request(&self, request: &Request)
+        -> Vec<Violation> {
+        self.report_violations_for_request_with_options(request, &RequestCheckOptions::default())
+    }
+    /// Like `report_violations_for_request`, but also runs the checks that `options` enables.
+    pub fn report_violations_for_request_with_options(&self, request: &Request, options: &RequestCheckOptions)
         -> Vec<Violation> {
         let mut violations = Vec::new();
         for policy in &self.0 {
             if policy.disposition == PolicyDisposition::Enforce { continue };
-            let violates = policy.does_request_violate_policy(request);
+            let violates = policy.does_request_violate_policy_with_options(request, options);
             if let Violates::Directive(directive) = violates {
-                let resource = ViolationResource::Url(request.url.clone());
-                violations.push(Violation { resource, directive });
+                violations.push(Violation::for_request(request, policy, directive, &options.browser_profile));
             }
         }
         violations
@@ -212,15 +550,18 @@ impl CspList {
     https://www.w3.org/TR/CSP/#should-block-request
     */
     pub fn should_request_be_blocked(&self, request: &Request) -> (CheckResult, Vec<Violation>) {
+        self.should_request_be_blocked_with_options(request, &RequestCheckOptions::default())
+    }
+    /// Like `should_request_be_blocked`, but also runs the checks that `options` enables.
+    pub fn should_request_be_blocked_with_options(&self, request: &Request, options: &RequestCheckOptions) -> (CheckResult, Vec<Violation>) {
         let mut result = CheckResult::Allowed;
         let mut violations = Vec::new();
         for policy in &self.0 {
             if policy.disposition == PolicyDisposition::Report { continue };
-            let violates = policy.does_request_violate_policy(request);
+            let violates = policy.does_request_violate_policy_with_options(request, options);
             if let Violates::Directive(directive) = violates {
                 result = CheckResult::Blocked;
-                let resource = ViolationResource::Url(request.url.clone());
-                violations.push(Violation { resource, directive });
+                violations.push(Violation::for_request(request, policy, directive, &options.browser_profile));
             }
         }
         (result, violations)
@@ -232,16 +573,18 @@ impl CspList {
     https://www.w3.org/TR/CSP/#should-block-response
     */
     pub fn should_response_to_request_be_blocked(&self, request: &Request, response: &Response)
+        -> (CheckResult, Vec<Violation>) {
+        self.should_response_to_request_be_blocked_with_options(request, response, &RequestCheckOptions::default())
+    }
+    /// Like `should_response_to_request_be_blocked`, but checks the response with `options`.
+    pub fn should_response_to_request_be_blocked_with_options(&self, request: &Request, response: &Response, options: &RequestCheckOptions)
         -> (CheckResult, Vec<Violation>) {
         let mut result = CheckResult::Allowed;
         let mut violations = Vec::new();
         for policy in &self.0 {
             for directive in &policy.directive_set {
-                if directive.post_request_check(request, response, policy) == CheckResult::Blocked {
-                    violations.push(Violation {
-                        resource: ViolationResource::Url(request.url.clone()),
-                        directive: directive.clone(),
-                    });
+                if directive.post_request_check_with_options(request, response, policy, options) == CheckResult::Blocked {
+                    violations.push(Violation::for_request(request, policy, directive.clone(), &options.browser_profile));
                     if policy.disposition == PolicyDisposition::Enforce {
                         result = CheckResult::Blocked;
                     }
@@ -251,10 +594,7 @@ impl CspList {
         for policy in &response.csp_list.0 {
             for directive in &policy.directive_set {
                 if directive.response_check(request, response, policy) == CheckResult::Blocked {
-                    violations.push(Violation {
-                        resource: ViolationResource::Url(request.url.clone()),
-                        directive: directive.clone(),
-                    });
+                    violations.push(Violation::for_request(request, policy, directive.clone(), &options.browser_profile));
                     if policy.disposition == PolicyDisposition::Enforce {
                         result = CheckResult::Blocked;
                     }
@@ -263,21 +603,191 @@ impl CspList {
         }
         (result, violations)
     }
+    /**
+    Given the CSP list of a global object, this algorithm returns Blocked if the global
+    may not make RTC connections, and reports violations for every `webrtc` directive
+    that does not allow them.
+
+    https://www.w3.org/TR/CSP/#should-block-rtc-connection
+    */
+    pub fn should_rtc_connections_be_blocked(&self) -> (CheckResult, Vec<Violation>) {
+        let mut result = CheckResult::Allowed;
+        let mut violations = Vec::new();
+        for policy in &self.0 {
+            for directive in &policy.directive_set {
+                if directive.webrtc_connection_check() == CheckResult::Allowed {
+                    continue;
+                }
+                violations.push(Violation::new(ViolationResource::None, policy, directive.clone(), ""));
+                if policy.disposition == PolicyDisposition::Enforce {
+                    result = CheckResult::Blocked;
+                }
+            }
+        }
+        (result, violations)
+    }
+    /// Whether the global object with this CSP list may make RTC connections.
+    ///
+    /// This is `should_rtc_connections_be_blocked`, without the violations.
+    pub fn is_webrtc_allowed(&self) -> bool {
+        self.should_rtc_connections_be_blocked().0 == CheckResult::Allowed
+    }
+    /**
+    Whether a plugin with the given media type may be loaded from `url`, according to the
+    `plugin-types` directives in this list.
+
+    Current browsers ignore `plugin-types`, so only call this to get the behaviour of older ones.
+
+    https://www.w3.org/TR/CSP2/#directive-plugin-types
+    */
+    pub fn should_plugin_be_blocked(&self, url: &Url, media_type: &str) -> (CheckResult, Vec<Violation>) {
+        let mut result = CheckResult::Allowed;
+        let mut violations = Vec::new();
+        let media_type = strip_leading_and_trailing_ascii_whitespace(media_type);
+        for policy in &self.0 {
+            for directive in &policy.directive_set {
+                if directive.name != "plugin-types" {
+                    continue;
+                }
+                if directive.value.iter().any(|t| ascii_case_insensitive_match(t, media_type)) {
+                    continue;
+                }
+                violations.push(Violation::new(ViolationResource::Url(url.clone()), policy, directive.clone(), ""));
+                if policy.disposition == PolicyDisposition::Enforce {
+                    result = CheckResult::Blocked;
+                }
+            }
+        }
+        (result, violations)
+    }
+    /**
+    Whether a navigation to `request.url` is allowed by the `navigate-to` directives in this list.
+
+    Current browsers ignore `navigate-to`, so only call this to get the behaviour of the draft.
+    `'unsafe-allow-redirects'` is not supported: every request in a redirect chain is checked.
+
+    https://www.w3.org/TR/2019/WD-CSP3-20191015/#directive-navigate-to
+    */
+    pub fn should_navigation_request_be_blocked(&self, request: &Request) -> (CheckResult, Vec<Violation>) {
+        self.should_navigation_request_be_blocked_with_options(request, &RequestCheckOptions::default())
+    }
+    /// Like `should_navigation_request_be_blocked`, but matches URLs with `options.scheme_registry`.
+    pub fn should_navigation_request_be_blocked_with_options(&self, request: &Request, options: &RequestCheckOptions) -> (CheckResult, Vec<Violation>) {
+        let mut result = CheckResult::Allowed;
+        let mut violations = Vec::new();
+        for policy in &self.0 {
+            for directive in &policy.directive_set {
+                if directive.name != "navigate-to" {
+                    continue;
+                }
+                if SourceList(&directive.value).does_request_match_source_list(request, &options.scheme_registry) == MatchResult::Matches {
+                    continue;
+                }
+                violations.push(Violation::for_request(request, policy, directive.clone(), &options.browser_profile));
+                if policy.disposition == PolicyDisposition::Enforce {
+                    result = CheckResult::Blocked;
+                }
+            }
+        }
+        (result, violations)
+    }
+    /**
+    The referrer policy set by the last enforced `referrer` directive in this list, translated
+    to its `Referrer-Policy` name, or `None` if there isn't one.
+
+    Current browsers ignore `referrer`, so only use this to get the behaviour of older ones.
+
+    https://www.w3.org/TR/2014/WD-CSP11-20140211/#referrer
+    */
+    pub fn legacy_referrer_policy(&self) -> Option<String> {
+        let directive = self.last_enforced_directive("referrer")?;
+        let value = directive.value.first()?.to_ascii_lowercase();
+        Some(match &value[..] {
+            "never" => "no-referrer",
+            "always" => "unsafe-url",
+            "default" => "no-referrer-when-downgrade",
+            "origin-when-crossorigin" => "origin-when-cross-origin",
+            other => other,
+        }.to_owned())
+    }
+    /**
+    The `X-XSS-Protection` header value equivalent to the last enforced `reflected-xss`
+    directive in this list, or `None` if there isn't one.
+
+    Current browsers ignore `reflected-xss`, so only use this to get the behaviour of older ones.
+
+    https://www.w3.org/TR/2014/WD-CSP11-20140211/#reflected-xss
+    */
+    pub fn legacy_x_xss_protection(&self) -> Option<&'static str> {
+        let directive = self.last_enforced_directive("reflected-xss")?;
+        let value = directive.value.first()?;
+        if ascii_case_insensitive_match(value, "allow") {
+            Some("0")
+        } else if ascii_case_insensitive_match(value, "filter") {
+            Some("1")
+        } else if ascii_case_insensitive_match(value, "block") {
+            Some("1; mode=block")
+        } else {
+            None
+        }
+    }
+    fn last_enforced_directive(&self, name: &str) -> Option<&Directive> {
+        self.0.iter()
+            .filter(|policy| policy.disposition == PolicyDisposition::Enforce)
+            .filter_map(|policy| policy.directive_set.iter().find(|d| d.name == name))
+            .next_back()
+    }
+    /**
+    Returns the union of the sandboxing flags set by every enforced `sandbox` directive
+    delivered in a header, or an empty set if there isn't one.
+    `sandbox` directives delivered in a `<meta>` element are ignored.
+
+    This deliberately deviates from HTML, which only uses the last enforced `sandbox` directive.
+    Every other directive in an enforced policy restricts the document on its own, so here a
+    later `sandbox allow-scripts` does not lift the script restriction of an earlier `sandbox`.
+
+    https://html.spec.whatwg.org/multipage/browsers.html#csp-derived-sandboxing-flags
+    */
+    pub fn sandboxing_flags(&self) -> SandboxingFlagSet {
+        self.0.iter()
+            .filter(|policy| policy.disposition == PolicyDisposition::Enforce && policy.source == PolicySource::Header)
+            .filter_map(|policy| policy.directive_set.iter().find(|d| d.name == "sandbox"))
+            .fold(SandboxingFlagSet::empty(), |flags, directive| flags | parse_a_sandboxing_directive(&directive.value[..]))
+    }
+    /**
+    Given the sandboxing flags a document's browsing context was created with
+    (see `sandboxing_directive::determine_the_creation_sandboxing_flags`),
+    returns the document's active sandboxing flag set, assuming this is the document's CSP list.
+
+    https://html.spec.whatwg.org/multipage/browsing-the-web.html#initialise-the-document-object
+    */
+    pub fn active_sandboxing_flags(&self, creation_sandboxing_flags: SandboxingFlagSet) -> SandboxingFlagSet {
+        creation_sandboxing_flags | self.sandboxing_flags()
+    }
     /// https://www.w3.org/TR/CSP/#should-block-inline
     pub fn should_elements_inline_type_behavior_be_blocked(&self, element: &Element, type_: InlineCheckType, source: &str) -> (CheckResult, Vec<Violation>) {
+        self.should_elements_inline_type_behavior_be_blocked_with_options(element, type_, source, &RequestCheckOptions::default())
+    }
+    /// Like `should_elements_inline_type_behavior_be_blocked`, but checks the element with `options`.
+    pub fn should_elements_inline_type_behavior_be_blocked_with_options(&self, element: &Element, type_: InlineCheckType, source: &str, options: &RequestCheckOptions)
+        -> (CheckResult, Vec<Violation>) {
         use CheckResult::*;
         let mut result = Allowed;
         let mut violations = Vec::new();
         for policy in &self.0 {
             for directive in &policy.directive_set {
-                if directive.inline_check(element, type_, policy, source) == Allowed {
+                if directive.inline_check_with_options(element, type_, policy, source, options) == Allowed {
                     continue;
                 }
-                let report_sample = directive.value.iter().filter(|t| &t[..] == "'report-sample'").next().is_some();
-                let violation = Violation {
-                    resource: ViolationResource::Inline{ report_sample },
-                    directive: directive.clone(),
-                };
+                let sample = obtain_a_sample(directive, source);
+                let effective_directive = get_the_effective_directive_for_profile(
+                    get_the_effective_directive_for_inline_checks(type_), &options.browser_profile);
+                let violation = Violation::new(
+                    ViolationResource::Inline{ sample },
+                    policy,
+                    directive.clone(),
+                    effective_directive,
+                );
                 violations.push(violation);
                 if policy.disposition == PolicyDisposition::Enforce {
                     result = Blocked;
@@ -286,9 +796,28 @@ impl CspList {
         }
         (result, violations)
     }
+    /**
+    Checks a navigation to a `javascript:` URL as inline script of type `Navigation`.
+
+    The script is the URL with its `javascript:` prefix removed, percent-decoded.
+    This is what `'unsafe-hashes'` hash-sources are compared against, and what the
+    violations' samples are taken from.
+    URLs with other schemes are always allowed.
+
+    https://html.spec.whatwg.org/multipage/browsing-the-web.html#navigate-to-a-javascript:-url
+    */
+    pub fn should_javascript_url_navigation_be_blocked(&self, url: &Url, element: &Element) -> (CheckResult, Vec<Violation>) {
+        if url.scheme() != "javascript" {
+            return (CheckResult::Allowed, Vec::new());
+        }
+        let script = &url.as_str()["javascript:".len()..];
+        let script = percent_encoding::percent_decode(script.as_bytes()).decode_utf8_lossy();
+        self.should_elements_inline_type_behavior_be_blocked(element, InlineCheckType::Navigation, &script)
+    }
 }
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Element<'a> {
     /// When there is no nonce, populate this member with `None`.
     ///
@@ -304,12 +833,18 @@ The valid values for type are "script", "script attribute", "style", and "style
 https://www.w3.org/TR/CSP/#should-block-inline
 */
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum InlineCheckType {
     Script,
     ScriptAttribute,
     Style,
     StyleAttribute,
     Navigation,
+    /// An inline `<script type="speculationrules">` element. It is checked like `Script`,
+    /// but is also allowed by the `'inline-speculation-rules'` keyword.
+    ///
+    /// https://wicg.github.io/nav-speculation/speculation-rules.html#content-security-policy
+    SpeculationRules,
 }
 
 /**
@@ -318,8 +853,10 @@ request to be validated
 https://fetch.spec.whatwg.org/#concept-request
 */
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Request {
     pub url: Url,
+    #[cfg_attr(feature = "serde", serde(with = "serde_origin"))]
     pub origin: Origin,
     pub redirect_count: u32,
     pub destination: Destination,
@@ -330,6 +867,7 @@ pub struct Request {
 }
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum ParserMetadata {
     ParserInserted,
     NotParserInserted,
@@ -339,7 +877,9 @@ pub enum ParserMetadata {
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub enum Initiator {
+    /// Also used for prefetches triggered by speculation rules.
     Prefetch,
+    /// Also used for prerenders triggered by speculation rules.
     Prerender,
     Fetch,
     None,
@@ -353,8 +893,12 @@ pub enum Destination {
     AudioWorklet,
     Document,
     Embed,
+    FencedFrame,
     Font,
+    Frame,
+    IFrame,
     Image,
+    Json,
     Manifest,
     Object,
     PaintWorklet,
@@ -362,9 +906,11 @@ pub enum Destination {
     Script,
     ServiceWorker,
     SharedWorker,
+    SpeculationRules,
     Style,
     Track,
     Video,
+    WebIdentity,
     Worker,
     Xslt,
 }
@@ -373,10 +919,7 @@ impl Destination {
     /// https://fetch.spec.whatwg.org/#request-destination-script-like
     pub fn is_script_like(self) -> bool {
         use Destination::*;
-        match self {
-            AudioWorklet | PaintWorklet | Script | ServiceWorker | SharedWorker | Worker | Xslt => true,
-            _ => false
-        }
+        matches!(self, AudioWorklet | PaintWorklet | Script | ServiceWorker | SharedWorker | Worker | Xslt)
     }
 }
 
@@ -386,6 +929,7 @@ response to be validated
 https://fetch.spec.whatwg.org/#concept-response
 */
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Response {
     pub csp_list: CspList,
     pub url: Url,
@@ -398,21 +942,103 @@ violation information
 https://www.w3.org/TR/CSP/#violation
 */
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct Violation {
+    /// The URL of the global object the violation occurred in.
+    ///
+    /// This library does not know about global objects, so it always leaves this as `None`.
+    /// Fill it in before reporting the violation.
+    pub url: Option<Url>,
+    /// The HTTP status code of the resource the global object was loaded from.
+    ///
+    /// This library always leaves this as `0`. Fill it in before reporting the violation.
+    pub status: u16,
     pub resource: ViolationResource,
+    /// The directive that was violated, as it appears in the policy.
     pub directive: Directive,
+    /// The name of the directive whose enforcement caused the violation, after fallback.
+    ///
+    /// For example, if `default-src` blocks a script, this is `script-src-elem`.
+    pub effective_directive: String,
+    /// The policy that was violated.
+    pub policy: Policy,
+    pub disposition: PolicyDisposition,
+    /// This library does not run scripts, so it always leaves this as `None`.
+    /// Fill it in before reporting the violation.
+    pub source_location: Option<SourceLocation>,
+}
+
+impl Violation {
+    /// https://www.w3.org/TR/CSP/#create-violation-for-global
+    fn new(resource: ViolationResource, policy: &Policy, directive: Directive, effective_directive: &str) -> Violation {
+        let effective_directive = if effective_directive.is_empty() {
+            directive.name.clone()
+        } else {
+            effective_directive.to_owned()
+        };
+        Violation {
+            url: None,
+            status: 0,
+            resource,
+            directive,
+            effective_directive,
+            policy: policy.clone(),
+            disposition: policy.disposition,
+            source_location: None,
+        }
+    }
+    /// https://www.w3.org/TR/CSP/#create-violation-for-request
+    fn for_request(request: &Request, policy: &Policy, directive: Directive, profile: &BrowserProfile) -> Violation {
+        // Only fetch directives fall back on each other. Any other directive that blocks a
+        // request or its response, like `frame-ancestors` or `sandbox`, is its own effective directive.
+        let effective_directive = get_the_effective_directive_for_request(request);
+        let is_fetch_directive = get_fetch_directive_fallback_list(effective_directive, profile)
+            .contains(&&directive.name[..]);
+        let effective_directive = if is_fetch_directive {
+            get_the_effective_directive_for_profile(effective_directive, profile)
+        } else {
+            ""
+        };
+        Violation::new(
+            ViolationResource::Url(request.url.clone()),
+            policy,
+            directive,
+            effective_directive,
+        )
+    }
+}
+
+/**
+the location of the script that caused a violation
+
+https://www.w3.org/TR/CSP/#violation-source-file
+*/
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct SourceLocation {
+    pub source_file: Url,
+    pub line_number: u32,
+    pub column_number: u32,
 }
 
 /**
 violation information
 
 https://www.w3.org/TR/CSP/#violation
+
+This enum is non-exhaustive, since the spec may add more kinds of resources.
 */
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+#[non_exhaustive]
 pub enum ViolationResource {
+    /// The spec leaves the resource null, as it does for WebRTC connections.
+    None,
     Url(Url),
     Inline {
-        report_sample: bool,
+        /// The first 40 characters of the inline source, present only if the violated
+        /// directive contains `'report-sample'`.
+        sample: Option<String>,
     },
 }
 
@@ -438,7 +1064,7 @@ pub enum Violates {
 }
 
 /// https://www.w3.org/TR/CSP/#policy-disposition
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum PolicyDisposition {
     Enforce,
@@ -481,157 +1107,199 @@ impl Directive {
     }
     /// https://www.w3.org/TR/CSP/#directive-pre-request-check
     pub fn pre_request_check(&self, request: &Request, policy: &Policy) -> CheckResult {
+        self.pre_request_check_with_options(request, policy, &RequestCheckOptions::default())
+    }
+    fn pre_request_check_with_options(&self, request: &Request, policy: &Policy, options: &RequestCheckOptions) -> CheckResult {
         use CheckResult::*;
+        let profile = &options.browser_profile;
+        let schemes = &*options.scheme_registry;
         match &self.name[..] {
             "child-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "child-src", policy) {
+                if !should_fetch_directive_execute(name, "child-src", policy, profile) {
+                    return Allowed;
+                }
+                let name = get_the_effective_directive_for_profile(name, profile);
+                if name == "child-src" {
+                    if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
+                        return Blocked;
+                    }
                     return Allowed;
                 }
                 (Directive {
                     name: String::from(name),
                     value: self.value.clone(),
-                }).pre_request_check(request, policy)
+                }).pre_request_check_with_options(request, policy, options)
             },
             "connect-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "connect-src", policy) {
+                if !should_fetch_directive_execute(name, "connect-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "default-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "default-src", policy) {
+                if !should_fetch_directive_execute(name, "default-src", policy, profile) {
+                    return Allowed;
+                }
+                if name == "default-src" {
+                    if options.legacy_directives == LegacyDirectives::Enforce && policy.contains_a_directive_whose_name_is("prefetch-src") {
+                        return Allowed;
+                    }
+                    if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
+                        return Blocked;
+                    }
                     return Allowed;
                 }
+                let name = get_the_effective_directive_for_profile(name, profile);
                 (Directive {
                     name: String::from(name),
                     value: self.value.clone(),
-                }).pre_request_check(request, policy)
+                }).pre_request_check_with_options(request, policy, options)
             }
             "font-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "font-src", policy) {
+                if !should_fetch_directive_execute(name, "font-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "frame-src" => {
+            "fenced-frame-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "frame-src", policy) {
+                if !should_fetch_directive_execute(name, "fenced-frame-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "img-src" => {
+            "frame-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "img-src", policy) {
+                if !should_fetch_directive_execute(name, "frame-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "manifest-src" => {
+            "img-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "manifest-src", policy) {
+                if !should_fetch_directive_execute(name, "img-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "media-src" => {
+            "manifest-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "media-src", policy) {
+                if !should_fetch_directive_execute(name, "manifest-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "prefetch-src" => {
+            "media-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "prefetch-src", policy) {
+                if !should_fetch_directive_execute(name, "media-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "object-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "object-src", policy) {
+                if !should_fetch_directive_execute(name, "object-src", policy, profile) {
                     return Allowed;
                 }
-                if SourceList(&self.value[..]).does_request_match_source_list(request) == DoesNotMatch {
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "script-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "script-src", policy) {
+                if !should_fetch_directive_execute(name, "script-src", policy, profile) {
                     return Allowed;
                 }
-                script_directives_prerequest_check(request, self)
+                script_directives_prerequest_check(request, self, options)
             }
             "script-src-elem" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "script-src-elem", policy) {
+                if !should_fetch_directive_execute(name, "script-src-elem", policy, profile) {
                     return Allowed;
                 }
-                script_directives_prerequest_check(request, self)
+                script_directives_prerequest_check(request, self, options)
             }
             "style-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "style-src", policy) {
+                if !should_fetch_directive_execute(name, "style-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_nonce_match_source_list(&request.nonce) == Matches {
+                if source_list.does_nonce_match_source_list(&request.nonce, profile) == Matches {
                     return Allowed;
                 }
-                if source_list.does_request_match_source_list(request) == DoesNotMatch {
+                if source_list.does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "style-src-elem" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "style-src-elem", policy) {
+                if !should_fetch_directive_execute(name, "style-src-elem", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_nonce_match_source_list(&request.nonce) == Matches {
+                if source_list.does_nonce_match_source_list(&request.nonce, profile) == Matches {
                     return Allowed;
                 }
-                if source_list.does_request_match_source_list(request) == DoesNotMatch {
+                if source_list.does_request_match_source_list(request, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "worker-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "worker-src", policy) {
+                if !should_fetch_directive_execute(name, "worker-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_request_match_source_list(request) == DoesNotMatch {
+                if source_list.does_request_match_source_list(request, schemes) == DoesNotMatch {
+                    return Blocked;
+                }
+                Allowed
+            }
+            "prefetch-src" if options.legacy_directives == LegacyDirectives::Enforce => {
+                if request.initiator != Initiator::Prefetch && request.initiator != Initiator::Prerender {
+                    return Allowed;
+                }
+                if SourceList(&self.value[..]).does_request_match_source_list(request, schemes) == DoesNotMatch {
+                    return Blocked;
+                }
+                Allowed
+            }
+            "block-all-mixed-content" if options.legacy_directives == LegacyDirectives::Enforce => {
+                let origin_is_secure = match request.origin {
+                    Origin::Tuple(ref scheme, _, _) => schemes.is_secure(scheme),
+                    Origin::Opaque(_) => false,
+                };
+                let url_scheme = request.url.scheme();
+                if origin_is_secure && schemes.get(url_scheme).is_some() && !schemes.is_secure(url_scheme) {
                     return Blocked;
                 }
                 Allowed
@@ -641,165 +1309,199 @@ impl Directive {
     }
     /// https://www.w3.org/TR/CSP/#directive-post-request-check
     pub fn post_request_check(&self, request: &Request, response: &Response, policy: &Policy) -> CheckResult {
+        self.post_request_check_with_options(request, response, policy, &RequestCheckOptions::default())
+    }
+    fn post_request_check_with_options(&self, request: &Request, response: &Response, policy: &Policy, options: &RequestCheckOptions) -> CheckResult {
         use CheckResult::*;
+        let profile = &options.browser_profile;
+        let schemes = &*options.scheme_registry;
         match &self.name[..] {
             "child-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "child-src", policy) {
+                if !should_fetch_directive_execute(name, "child-src", policy, profile) {
+                    return Allowed;
+                }
+                let name = get_the_effective_directive_for_profile(name, profile);
+                if name == "child-src" {
+                    let source_list = SourceList(&self.value);
+                    if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
+                        return Blocked;
+                    }
                     return Allowed;
                 }
                 Directive {
                     name: name.to_owned(),
-                    value: self.value.clone()
-                }.post_request_check(request, response, policy)
+                    value: self.value.clone(),
+                }.post_request_check_with_options(request, response, policy, options)
             }
             "connect-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "connect-src", policy) {
+                if !should_fetch_directive_execute(name, "connect-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "default-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "default-src", policy) {
+                if !should_fetch_directive_execute(name, "default-src", policy, profile) {
+                    return Allowed;
+                }
+                if name == "default-src" {
+                    if options.legacy_directives == LegacyDirectives::Enforce && policy.contains_a_directive_whose_name_is("prefetch-src") {
+                        return Allowed;
+                    }
+                    let source_list = SourceList(&self.value);
+                    if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
+                        return Blocked;
+                    }
                     return Allowed;
                 }
+                let name = get_the_effective_directive_for_profile(name, profile);
                 Directive {
                     name: name.to_owned(),
                     value: self.value.clone(),
-                }.post_request_check(request, response, policy)
+                }.post_request_check_with_options(request, response, policy, options)
             }
             "font-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "font-src", policy) {
+                if !should_fetch_directive_execute(name, "font-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "frame-src" => {
+            "fenced-frame-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "frame-src", policy) {
+                if !should_fetch_directive_execute(name, "fenced-frame-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "img-src" => {
+            "frame-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "img-src", policy) {
+                if !should_fetch_directive_execute(name, "frame-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "manifest-src" => {
+            "img-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "manifest-src", policy) {
+                if !should_fetch_directive_execute(name, "img-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "media-src" => {
+            "manifest-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "media-src", policy) {
+                if !should_fetch_directive_execute(name, "manifest-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
-            "prefetch-src" => {
+            "media-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "prefetch-src", policy) {
+                if !should_fetch_directive_execute(name, "media-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "object-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "object-src", policy) {
+                if !should_fetch_directive_execute(name, "object-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "script-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "script-src", policy) {
+                if !should_fetch_directive_execute(name, "script-src", policy, profile) {
                     return Allowed;
                 }
-                script_directives_postrequest_check(request, response, self)
+                script_directives_postrequest_check(request, response, self, options)
             }
             "script-src-elem" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "script-src-elem", policy) {
+                if !should_fetch_directive_execute(name, "script-src-elem", policy, profile) {
                     return Allowed;
                 }
-                script_directives_postrequest_check(request, response, self)
+                script_directives_postrequest_check(request, response, self, options)
             }
             "style-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "style-src", policy) {
+                if !should_fetch_directive_execute(name, "style-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_nonce_match_source_list(&request.nonce) == Matches {
+                if source_list.does_nonce_match_source_list(&request.nonce, profile) == Matches {
                     return Allowed;
                 }
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "style-src-elem" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "style-src-elem", policy) {
+                if !should_fetch_directive_execute(name, "style-src-elem", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_nonce_match_source_list(&request.nonce) == Matches {
+                if source_list.does_nonce_match_source_list(&request.nonce, profile) == Matches {
                     return Allowed;
                 }
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "worker-src" => {
                 let name = get_the_effective_directive_for_request(request);
-                if !should_fetch_directive_execute(name, "worker-src", policy) {
+                if !should_fetch_directive_execute(name, "worker-src", policy, profile) {
+                    return Allowed;
+                }
+                let source_list = SourceList(&self.value);
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
+                    return Blocked;
+                }
+                Allowed
+            }
+            "prefetch-src" if options.legacy_directives == LegacyDirectives::Enforce => {
+                if request.initiator != Initiator::Prefetch && request.initiator != Initiator::Prerender {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+                if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
@@ -807,6 +1509,35 @@ impl Directive {
             _ => Allowed,
         }
     }
+    /**
+    Blocks script and style requests without usable integrity metadata, if this is a
+    `require-sri-for` directive that lists their destination, as `script` or `style`.
+    Other directives allow every request.
+
+    Only `CspList::should_request_be_blocked_with_options` runs this check, because the
+    directive never fully shipped in browsers.
+
+    https://w3c.github.io/webappsec-subresource-integrity/#opt-in-require-sri-for
+    */
+    pub fn require_sri_for_check(&self, request: &Request) -> CheckResult {
+        if self.name != "require-sri-for" {
+            return CheckResult::Allowed;
+        }
+        let token = if request.destination.is_script_like() {
+            "script"
+        } else if request.destination == Destination::Style {
+            "style"
+        } else {
+            return CheckResult::Allowed;
+        };
+        if !self.value.iter().any(|t| ascii_case_insensitive_match(t, token)) {
+            return CheckResult::Allowed;
+        }
+        match parse_subresource_integrity_metadata(&request.integrity_metadata) {
+            SubresourceIntegrityMetadata::IntegritySources(ref sources) if !sources.is_empty() => CheckResult::Allowed,
+            _ => CheckResult::Blocked,
+        }
+    }
     /// https://www.w3.org/TR/CSP/#directive-response-check
     pub fn response_check(&self, request: &Request, _response: &Response, policy: &Policy) -> CheckResult {
         use CheckResult::*;
@@ -832,82 +1563,99 @@ impl Directive {
             _ => Allowed,
         }
     }
+    /// The inline check for the "webrtc connection" type, which only the `webrtc` directive
+    /// blocks, unless its value is exactly `'allow'`.
+    ///
+    /// https://www.w3.org/TR/CSP/#webrtc-inline-check
+    fn webrtc_connection_check(&self) -> CheckResult {
+        if self.name != "webrtc" {
+            return CheckResult::Allowed;
+        }
+        if self.value.len() == 1 && ascii_case_insensitive_match(&self.value[0], "'allow'") {
+            return CheckResult::Allowed;
+        }
+        CheckResult::Blocked
+    }
     /// https://www.w3.org/TR/CSP/#directive-inline-check
     pub fn inline_check(&self, element: &Element, type_: InlineCheckType, policy: &Policy, source: &str) -> CheckResult {
+        self.inline_check_with_options(element, type_, policy, source, &RequestCheckOptions::default())
+    }
+    fn inline_check_with_options(&self, element: &Element, type_: InlineCheckType, policy: &Policy, source: &str, options: &RequestCheckOptions) -> CheckResult {
         use CheckResult::*;
+        let profile = &options.browser_profile;
         match &self.name[..] {
             "default-src" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "default-src", policy) {
+                if !should_fetch_directive_execute(name, "default-src", policy, profile) {
                     return Allowed;
                 }
                 Directive {
-                    name: name.to_owned(),
-                    value: self.value.clone()
-                }.inline_check(element, type_, policy, source)
+                    name: get_the_effective_directive_for_profile(name, profile).to_owned(),
+                    value: self.value.clone(),
+                }.inline_check_with_options(element, type_, policy, source, options)
             }
             "script-src" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "script-src", policy) {
+                if !should_fetch_directive_execute(name, "script-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source) == DoesNotMatch {
+                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source, profile) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "script-src-elem" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "script-src-elem", policy) {
+                if !should_fetch_directive_execute(name, "script-src-elem", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source) == DoesNotMatch {
+                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source, profile) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "script-src-attr" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "script-src-attr", policy) {
+                if !should_fetch_directive_execute(name, "script-src-attr", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source) == DoesNotMatch {
+                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source, profile) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "style-src" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "style-src", policy) {
+                if !should_fetch_directive_execute(name, "style-src", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source) == DoesNotMatch {
+                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source, profile) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "style-src-elem" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "style-src-elem", policy) {
+                if !should_fetch_directive_execute(name, "style-src-elem", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source) == DoesNotMatch {
+                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source, profile) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
             }
             "style-src-attr" => {
                 let name = get_the_effective_directive_for_inline_checks(type_);
-                if !should_fetch_directive_execute(name, "style-src-attr", policy) {
+                if !should_fetch_directive_execute(name, "style-src-attr", policy, profile) {
                     return Allowed;
                 }
                 let source_list = SourceList(&self.value);
-                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source) == DoesNotMatch {
+                if source_list.does_element_match_source_list_for_type_and_source(element, type_, source, profile) == DoesNotMatch {
                     return Blocked;
                 }
                 Allowed
@@ -917,11 +1665,25 @@ impl Directive {
     }
 }
 
+/**
+If the directive contains `'report-sample'`, this returns the first 40 characters of source.
+Otherwise, it returns `None`, so that the source text does not leak into reports.
+
+https://www.w3.org/TR/CSP/#should-block-inline
+*/
+fn obtain_a_sample(directive: &Directive, source: &str) -> Option<String> {
+    if directive.value.iter().any(|t| ascii_case_insensitive_match(t, "'report-sample'")) {
+        Some(source.chars().take(40).collect())
+    } else {
+        None
+    }
+}
+
 /// https://www.w3.org/TR/CSP/#effective-directive-for-inline-check
 fn get_the_effective_directive_for_inline_checks(type_: InlineCheckType) -> &'static str {
     use InlineCheckType::*;
     match type_ {
-        Script | Navigation => "script-src-elem",
+        Script | Navigation | SpeculationRules => "script-src-elem",
         ScriptAttribute => "script-src-attr",
         Style => "style-src-elem",
         StyleAttribute => "style-src-attr",
@@ -929,11 +1691,13 @@ fn get_the_effective_directive_for_inline_checks(type_: InlineCheckType) -> &'st
 }
 
 /// https://www.w3.org/TR/CSP/#script-pre-request
-fn script_directives_prerequest_check(request: &Request, directive: &Directive) -> CheckResult {
+fn script_directives_prerequest_check(request: &Request, directive: &Directive, options: &RequestCheckOptions) -> CheckResult {
     use CheckResult::*;
+    let profile = &options.browser_profile;
+    let schemes = &*options.scheme_registry;
     if request_is_script_like(request) {
         let source_list = SourceList(&directive.value[..]);
-        if source_list.does_nonce_match_source_list(&request.nonce) == Matches {
+        if source_list.does_nonce_match_source_list(&request.nonce, profile) == Matches {
             return Allowed;
         }
         let integrity_expressions: Vec<HashFunction> = directive.value.iter()
@@ -946,12 +1710,12 @@ fn script_directives_prerequest_check(request: &Request, directive: &Directive)
                 None
             })
             .collect();
-        if !integrity_expressions.is_empty() {
+        if profile.external_script_hashes && !integrity_expressions.is_empty() {
             let integrity_sources = parse_subresource_integrity_metadata(&request.integrity_metadata);
             if let SubresourceIntegrityMetadata::IntegritySources(integrity_sources) = integrity_sources {
                 let mut bypass_due_to_integrity_match = true;
                 for source in &integrity_sources {
-                    if integrity_expressions.iter().filter(|&ex| ex == source).next().is_none() {
+                    if !integrity_expressions.iter().any(|ex| ex == source) {
                         bypass_due_to_integrity_match = false;
                     }
                 }
@@ -959,15 +1723,15 @@ fn script_directives_prerequest_check(request: &Request, directive: &Directive)
                     return Allowed;
                 }
             }
-            if directive.value.iter().filter(|ex| ascii_case_insensitive_match(ex, "'strict-dynamic'")).next().is_some() {
-                if request.parser_metadata == ParserMetadata::ParserInserted {
-                    return Blocked;
-                } else {
-                    return Allowed;
-                }
+        }
+        if profile.strict_dynamic && directive.value.iter().any(|ex| ascii_case_insensitive_match(ex, "'strict-dynamic'")) {
+            if request.parser_metadata == ParserMetadata::ParserInserted {
+                return Blocked;
+            } else {
+                return Allowed;
             }
         }
-        if source_list.does_request_match_source_list(request) == DoesNotMatch {
+        if source_list.does_request_match_source_list(request, schemes) == DoesNotMatch {
             return Blocked;
         }
     }
@@ -975,17 +1739,19 @@ fn script_directives_prerequest_check(request: &Request, directive: &Directive)
 }
 
 /// https://www.w3.org/TR/CSP/#script-post-request
-fn script_directives_postrequest_check(request: &Request, response: &Response, directive: &Directive) -> CheckResult {
+fn script_directives_postrequest_check(request: &Request, response: &Response, directive: &Directive, options: &RequestCheckOptions) -> CheckResult {
     use CheckResult::*;
+    let profile = &options.browser_profile;
+    let schemes = &*options.scheme_registry;
     if request_is_script_like(request) {
         let source_list = SourceList(&directive.value[..]);
-        if source_list.does_nonce_match_source_list(&request.nonce) == Matches {
+        if source_list.does_nonce_match_source_list(&request.nonce, profile) == Matches {
             return Allowed;
         }
-        if directive.value.iter().filter(|ex| ascii_case_insensitive_match(ex, "'strict-dynamic'")).next().is_some() && request.parser_metadata != ParserMetadata::ParserInserted {
+        if profile.strict_dynamic && directive.value.iter().any(|ex| ascii_case_insensitive_match(ex, "'strict-dynamic'")) && request.parser_metadata != ParserMetadata::ParserInserted {
             return Allowed;
         }
-        if source_list.does_response_to_request_match_source_list(request, response) == DoesNotMatch {
+        if source_list.does_response_to_request_match_source_list(request, response, schemes) == DoesNotMatch {
             return Blocked;
         }
     }
@@ -993,13 +1759,16 @@ fn script_directives_postrequest_check(request: &Request, response: &Response, d
 }
 
 /// https://fetch.spec.whatwg.org/#request-destination-script-like
+///
+/// Speculation rules are not script-like in Fetch, but they are checked as scripts here:
+/// https://wicg.github.io/nav-speculation/speculation-rules.html#content-security-policy
 fn request_is_script_like(request: &Request) -> bool {
-    request.destination.is_script_like()
+    request.destination.is_script_like() || request.destination == Destination::SpeculationRules
 }
 
 /// https://www.w3.org/TR/CSP/#should-directive-execute
-fn should_fetch_directive_execute(effective_directive_name: &str, directive_name: &str, policy: &Policy) -> bool {
-    let directive_fallback_list = get_fetch_directive_fallback_list(effective_directive_name);
+fn should_fetch_directive_execute(effective_directive_name: &str, directive_name: &str, policy: &Policy, profile: &BrowserProfile) -> bool {
+    let directive_fallback_list = get_fetch_directive_fallback_list(effective_directive_name, profile);
     for fallback_directive in directive_fallback_list {
         if directive_name == *fallback_directive {
             return true;
@@ -1011,9 +1780,26 @@ fn should_fetch_directive_execute(effective_directive_name: &str, directive_name
     false
 }
 
+/// The first directive in the fallback list of `effective_directive_name` that `profile`
+/// supports. This is the directive that `default-src` and `child-src` defer to, and the
+/// effective directive of the violations they report, like `script-src` instead of
+/// `script-src-elem` in CSP2.
+fn get_the_effective_directive_for_profile(effective_directive_name: &'static str, profile: &BrowserProfile) -> &'static str {
+    get_fetch_directive_fallback_list(effective_directive_name, profile).first().cloned().unwrap_or(effective_directive_name)
+}
+
 /// https://www.w3.org/TR/CSP/#directive-fallback-list
-fn get_fetch_directive_fallback_list(directive_name: &str) -> &'static [&'static str] {
+///
+/// Directives that `profile` does not support are left out, and workers and frames fall back
+/// the way they did in CSP1 and CSP2.
+fn get_fetch_directive_fallback_list(directive_name: &str, profile: &BrowserProfile) -> &'static [&'static str] {
     match directive_name {
+        "script-src-elem" | "script-src-attr" if !profile.element_and_attribute_directives => &["script-src", "default-src"],
+        "style-src-elem" | "style-src-attr" if !profile.element_and_attribute_directives => &["style-src", "default-src"],
+        "worker-src" if !profile.child_src => &["script-src", "default-src"],
+        "worker-src" if !profile.worker_src => &["child-src", "default-src"],
+        "frame-src" if !profile.child_src => &["frame-src", "default-src"],
+        "fenced-frame-src" if !profile.child_src => &["fenced-frame-src", "frame-src", "default-src"],
         "script-src-elem" => &["script-src-elem", "script-src", "default-src"],
         "script-src-attr" => &["script-src-attr", "script-src", "default-src"],
         "style-src-elem"  => &["style-src-elem", "style-src", "default-src"],
@@ -1021,42 +1807,52 @@ fn get_fetch_directive_fallback_list(directive_name: &str) -> &'static [&'static
         "worker-src"      => &["worker-src", "child-src", "script-src", "default-src"],
         "connect-src"     => &["connect-src", "default-src"],
         "manifest-src"    => &["manifest-src", "default-src"],
-        "prefetch-src"    => &["prefetch-src", "default-src"],
         "object-src"      => &["object-src", "default-src"],
         "frame-src"       => &["frame-src", "child-src", "default-src"],
+        "fenced-frame-src" => &["fenced-frame-src", "frame-src", "child-src", "default-src"],
         "media-src"       => &["media-src", "default-src"],
         "font-src"        => &["font-src", "default-src"],
         "img-src"         => &["img-src", "default-src"],
+        "default-src"     => &["default-src"],
         _                 => &[],
     }
 }
 
 /// https://www.w3.org/TR/CSP/#effective-directive-for-a-request
+///
+/// `fenced-frame-src` comes from https://wicg.github.io/fenced-frame/#csp-algorithms,
+/// and `speculationrules` from https://wicg.github.io/nav-speculation/speculation-rules.html#content-security-policy.
+///
+/// `Document` is treated like `IFrame`, as it was before Fetch had separate frame destinations.
+/// Reports are not governed by any fetch directive, so this returns an empty string for them.
 fn get_the_effective_directive_for_request(request: &Request) -> &'static str {
     use Initiator::*;
     use Destination::*;
-    if request.initiator == Fetch || request.destination == Destination::None {
-        return "connect-src";
-    }
     if request.initiator == Prefetch || request.initiator == Prerender {
-        return "prefetch-src";
+        return "default-src";
+    }
+    if request.initiator == Fetch {
+        return "connect-src";
     }
     match request.destination {
+        Destination::None | Json | WebIdentity => "connect-src",
         Manifest => "manifest-src",
         Object | Embed => "object-src",
-        Document => "frame-src",
+        Document | Frame | IFrame => "frame-src",
+        FencedFrame => "fenced-frame-src",
         Audio | Track | Video => "media-src",
         Font => "font-src",
         Image => "img-src",
         Style => "style-src-elem",
-        Script | Xslt => "script-src-elem",
+        Script | Xslt | AudioWorklet | PaintWorklet | SpeculationRules => "script-src-elem",
         ServiceWorker | SharedWorker | Worker => "worker-src",
-        _ => "",
+        Report => "",
     }
 }
 
 /// https://www.w3.org/TR/CSP/#match-element-to-source-list
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum MatchResult {
     Matches,
     DoesNotMatch,
@@ -1078,8 +1874,11 @@ lazy_static!{
     static ref SCHEME_SOURCE_GRAMMAR: Regex =
         Regex::new(r#"^(?P<scheme>[a-zA-Z][a-zA-Z0-9\+\-\.]*):$"#).unwrap();
     /// https://www.w3.org/TR/CSP/#grammardef-host-source
+    ///
+    /// Hosts may also contain non-ASCII characters and a trailing dot. They are normalized
+    /// by `host_part_match`.
     static ref HOST_SOURCE_GRAMMAR: Regex =
-        Regex::new(r#"^((?P<scheme>[a-zA-Z][a-zA-Z0-9\+\-\.]*)://)?(?P<host>\*|(\*\.)?[a-zA-Z0-9\-]+(\.[a-zA-Z0-9\-]+)*)(?P<port>:(\*|[0-9]+))?(?P<path>/([:@%!\$&'\(\)\*\+,;=0-9a-zA-Z\-\._~]+)?(/[:@%!\$&'\(\)\*\+,;=0-9a-zA-Z\-\._~]*)*)?$"#).unwrap();
+        Regex::new(r#"^((?P<scheme>[a-zA-Z][a-zA-Z0-9\+\-\.]*)://)?(?P<host>\*|(\*\.)?[a-zA-Z0-9\-\x{80}-\x{10FFFF}]+(\.[a-zA-Z0-9\-\x{80}-\x{10FFFF}]+)*\.?)(?P<port>:(\*|[0-9]+))?(?P<path>/([:@%!\$&'\(\)\*\+,;=0-9a-zA-Z\-\._~]+)?(/[:@%!\$&'\(\)\*\+,;=0-9a-zA-Z\-\._~]*)*)?$"#).unwrap();
     /// https://www.w3.org/TR/CSP/#grammardef-hash-source
     static ref HASH_SOURCE_GRAMMAR: Regex =
         Regex::new(r#"^'(?P<algorithm>sha256|sha384|sha512)-(?P<value>[a-zA-Z0-9\+/\-_]+=*)'$"#).unwrap();
@@ -1091,8 +1890,8 @@ struct SourceList<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item
 
 impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> SourceList<'a, U, I> {
     /// https://www.w3.org/TR/CSP/#match-nonce-to-source-list
-    fn does_nonce_match_source_list(&self, nonce: &str) -> MatchResult {
-        if nonce.is_empty() { return DoesNotMatch };
+    fn does_nonce_match_source_list(&self, nonce: &str, profile: &BrowserProfile) -> MatchResult {
+        if nonce.is_empty() || !profile.nonces_and_hashes { return DoesNotMatch };
         for expression in self.0.clone().into_iter() {
             if let Some(captures) = NONCE_SOURCE_GRAMMAR.captures(expression.borrow()) {
                 if let Some(captured_nonce) = captures.name("n") {
@@ -1105,11 +1904,12 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
         DoesNotMatch
     }
     /// https://www.w3.org/TR/CSP/#match-request-to-source-list
-    fn does_request_match_source_list(&self, request: &Request) -> MatchResult {
+    fn does_request_match_source_list(&self, request: &Request, schemes: &SchemeRegistry) -> MatchResult {
         self.does_url_match_source_list_in_origin_with_redirect_count(
             &request.url,
             &request.origin,
             request.redirect_count,
+            schemes,
         )
     }
     /// https://www.w3.org/TR/CSP/#match-url-to-source-list
@@ -1118,6 +1918,7 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
         url: &Url,
         origin: &Origin,
         redirect_count: u32,
+        schemes: &SchemeRegistry,
     ) -> MatchResult {
         for expression in self.0.clone().into_iter().map(Borrow::borrow) {
             if NONE_SOURCE_GRAMMAR.is_match(expression) { continue };
@@ -1125,7 +1926,8 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
                 url,
                 expression,
                 origin,
-                redirect_count
+                redirect_count,
+                schemes,
             );
             if result == Matches {
                 return Matches;
@@ -1139,14 +1941,26 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
         element: &Element,
         type_: InlineCheckType,
         source: &str,
+        profile: &BrowserProfile,
     ) -> MatchResult {
-        if self.does_a_source_list_allow_all_inline_behavior_for_type(type_) == AllowResult::Allows {
+        use InlineCheckType::*;
+        if self.does_a_source_list_allow_all_inline_behavior_for_type(type_, profile) == AllowResult::Allows {
             return Matches;
         }
-        if type_ == InlineCheckType::Script || type_ == InlineCheckType::Style {
+        if type_ == SpeculationRules {
+            for expression in self.0.clone().into_iter().map(Borrow::borrow) {
+                if ascii_case_insensitive_match(expression, "'inline-speculation-rules'") {
+                    return Matches;
+                }
+            }
+        }
+        if !profile.nonces_and_hashes {
+            return DoesNotMatch;
+        }
+        if type_ == Script || type_ == Style || type_ == SpeculationRules {
             if let Some(nonce) = element.nonce.as_ref() {
                 for expression in self.0.clone().into_iter().map(Borrow::borrow) {
-                    if let Some(captures) = NONCE_SOURCE_GRAMMAR.captures(expression.borrow()) {
+                    if let Some(captures) = NONCE_SOURCE_GRAMMAR.captures(expression) {
                         if let Some(captured_nonce) = captures.name("n") {
                             if nonce == captured_nonce.as_str() {
                                 return Matches;
@@ -1158,14 +1972,14 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
         }
         let mut unsafe_hashes = false;
         for expression in self.0.clone().into_iter().map(Borrow::borrow) {
-            if ascii_case_insensitive_match(expression, "'unsafe-hashes'") {
+            if profile.unsafe_hashes && ascii_case_insensitive_match(expression, "'unsafe-hashes'") {
                 unsafe_hashes = true;
                 break;
             }
         }
-        if type_ == InlineCheckType::Script || type_ == InlineCheckType::Style || unsafe_hashes {
+        if type_ == Script || type_ == Style || type_ == SpeculationRules || unsafe_hashes {
             for expression in self.0.clone().into_iter().map(Borrow::borrow) {
-                if let Some(captures) = HASH_SOURCE_GRAMMAR.captures(expression.borrow()) {
+                if let Some(captures) = HASH_SOURCE_GRAMMAR.captures(expression) {
                     if let (Some(algorithm), Some(value)) = (captures.name("algorithm").and_then(|a| HashAlgorithm::from_name(a.as_str())), captures.name("value")) {
                         let actual = algorithm.apply(source);
                         let expected = value.as_str().replace('-', "+").replace('_', "/");
@@ -1179,14 +1993,14 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
         DoesNotMatch
     }
     /// https://www.w3.org/TR/CSP/#allow-all-inline
-    fn does_a_source_list_allow_all_inline_behavior_for_type(&self, type_: InlineCheckType) -> AllowResult {
+    fn does_a_source_list_allow_all_inline_behavior_for_type(&self, type_: InlineCheckType, profile: &BrowserProfile) -> AllowResult {
         use InlineCheckType::*;
         let mut allow_all_inline = false;
         for expression in self.0.clone().into_iter().map(Borrow::borrow) {
-            if HASH_SOURCE_GRAMMAR.is_match(expression) || NONCE_SOURCE_GRAMMAR.is_match(expression) {
+            if profile.nonces_and_hashes && (HASH_SOURCE_GRAMMAR.is_match(expression) || NONCE_SOURCE_GRAMMAR.is_match(expression)) {
                 return AllowResult::DoesNotAllow;
             }
-            if (type_ == Script || type_ == ScriptAttribute || type_ == Navigation) && expression == "'strict-dynamic'" {
+            if profile.strict_dynamic && (type_ == Script || type_ == ScriptAttribute || type_ == Navigation || type_ == SpeculationRules) && expression == "'strict-dynamic'" {
                 return AllowResult::DoesNotAllow;
             }
             if ascii_case_insensitive_match(expression, "'unsafe-inline'") {
@@ -1203,11 +2017,13 @@ impl<'a, U: 'a + ?Sized + Borrow<str>, I: Clone + IntoIterator<Item=&'a U>> Sour
     fn does_response_to_request_match_source_list(
         &self,
         request: &Request,
-        response: &Response) -> MatchResult {
+        response: &Response,
+        schemes: &SchemeRegistry) -> MatchResult {
         self.does_url_match_source_list_in_origin_with_redirect_count(
             &response.url,
             &request.origin,
             response.redirect_count,
+            schemes,
         )
     }
 }
@@ -1225,24 +2041,25 @@ fn does_url_match_expression_in_origin_with_redirect_count(
     expression: &str,
     origin: &Origin,
     redirect_count: u32,
+    schemes: &SchemeRegistry,
 ) -> MatchResult {
     let url_scheme = url.scheme();
     if expression == "*" {
-        if scheme_is_network(url_scheme) {
+        if schemes.is_network(url_scheme) {
             return Matches;
         }
-        return origin_scheme_part_match(origin, url_scheme);
+        return origin_scheme_part_match(origin, url_scheme, schemes);
     }
     if let Some(captures) = SCHEME_SOURCE_GRAMMAR.captures(expression) {
         if let Some(expression_scheme) = captures.name("scheme") {
-            return scheme_part_match(expression_scheme.as_str(), url_scheme);
+            return scheme_part_match(expression_scheme.as_str(), url_scheme, schemes);
         }
         // It should not be possible to match HOST_SOURCE_GRAMMAR without having a scheme part
         return DoesNotMatch;
     }
     if let Some(captures) = HOST_SOURCE_GRAMMAR.captures(expression) {
         let expr_has_scheme_part = if let Some(expression_scheme) = captures.name("scheme") {
-            if scheme_part_match(expression_scheme.as_str(), url_scheme) != Matches {
+            if scheme_part_match(expression_scheme.as_str(), url_scheme, schemes) != Matches {
                 return DoesNotMatch;
             }
             true
@@ -1255,20 +2072,19 @@ fn does_url_match_expression_in_origin_with_redirect_count(
             return DoesNotMatch;
         };
         if !expr_has_scheme_part &&
-            origin_scheme_part_match(origin, url.scheme()) != Matches {
+            origin_scheme_part_match(origin, url.scheme(), schemes) != Matches {
             return DoesNotMatch;
         }
         if let Some(expression_host) = captures.name("host") {
-            if host_part_match(expression_host.as_str(), &url_host.to_string()) != Matches {
+            if host_part_match(expression_host.as_str(), &url_host) != Matches {
                 return DoesNotMatch;
             }
         } else {
             // It should not be possible to match HOST_SOURCE_GRAMMAR without having a host part
             return DoesNotMatch;
         }
-        let port_part = captures.name("port").map(|port| port.as_str()).unwrap_or("");
-        let url_port = url_port(url);
-        if port_part_match(port_part, &url_port[..], url.scheme()) != Matches {
+        let port_part = captures.name("port").map(|port| &port.as_str()[1..]).unwrap_or("");
+        if port_part_match(port_part, url, schemes) != Matches {
             return DoesNotMatch;
         }
         let path_part = captures.name("path").map(|path_part| path_part.as_str()).unwrap_or("");
@@ -1286,15 +2102,16 @@ fn does_url_match_expression_in_origin_with_redirect_count(
         }
         if let &Origin::Tuple(ref scheme, ref host, port) = origin {
             let hosts_are_the_same = Some(host) == url.host().map(|p| p.to_owned()).as_ref();
-            let ports_are_the_same = Some(port) == url.port();
-            let origins_port_is_default_for_scheme = Some(port) == default_port(scheme);
-            let url_port_is_default_port_for_scheme = url.port() == default_port(scheme)
-                && default_port(scheme).is_some();
+            let url_port = url.port().or_else(|| schemes.default_port(url_scheme));
+            let ports_are_the_same = Some(port) == url_port;
+            let origins_port_is_default_for_scheme = Some(port) == schemes.default_port(scheme);
+            let url_port_is_default_port_for_scheme = url_port.is_some()
+                && url_port == schemes.default_port(url_scheme);
             let ports_are_default = url_port_is_default_port_for_scheme && origins_port_is_default_for_scheme;
             if hosts_are_the_same
                 && (ports_are_the_same || ports_are_default)
-                && ((url_scheme == "https" || url_scheme == "wss")
-                        || (scheme == "http" && (url_scheme == "http" || url_scheme == "ws"))) {
+                && (schemes.is_secure(url_scheme)
+                        || (!schemes.is_secure(scheme) && schemes.does_scheme_match(url_scheme, scheme))) {
                 return Matches;
             }
         }
@@ -1303,48 +2120,51 @@ fn does_url_match_expression_in_origin_with_redirect_count(
 }
 
 /// https://www.w3.org/TR/CSP/#match-hosts
-fn host_part_match(a: &str, b: &str) -> MatchResult {
+///
+/// Both hosts are compared after host parsing, so Unicode domains in A match their punycode
+/// form in B, case never matters, and a trailing dot is ignored.
+/// Wildcards only match domains, never IP addresses.
+fn host_part_match(a: &str, b: &Host<&str>) -> MatchResult {
     debug_assert!(!a.is_empty());
     if a.is_empty() {
         return DoesNotMatch;
     }
-    if a.as_bytes()[0] == b'*' {
-        let remaining = &a[1..];
-        debug_assert_eq!(&remaining[..1], ".");
-        if remaining.len() > b.len() {
-            return DoesNotMatch;
-        }
-        let remaining_b = &b[(b.len()-remaining.len())..];
-        debug_assert_eq!(remaining_b.len(), remaining.len());
-        if ascii_case_insensitive_match(remaining, remaining_b) {
+    if a == "*" {
+        return Matches;
+    }
+    if let Some(remaining) = a.strip_prefix("*.") {
+        let b = match *b {
+            Host::Domain(b) => b.trim_end_matches('.'),
+            _ => return DoesNotMatch,
+        };
+        let remaining = match Host::parse(remaining) {
+            Ok(Host::Domain(remaining)) => remaining,
+            _ => return DoesNotMatch,
+        };
+        let remaining = remaining.trim_end_matches('.');
+        if b.len() > remaining.len() && b.ends_with(remaining) && b.as_bytes()[b.len() - remaining.len() - 1] == b'.' {
             return Matches;
         } else {
             return DoesNotMatch;
         }
     }
-    if !ascii_case_insensitive_match(a, b) {
-        return DoesNotMatch;
-    }
-    lazy_static!{
-        static ref IPV4_ADDRESS_RULE: Regex =
-            Regex::new(r#"([0-9]|[1-9][0-9]|1[0-9][0-9]|2[0-4][0-9]|25[0-5])\.([0-9]|[1-9][0-9]|1[0-9][0-9]|2[0-4][0-9]|25[0-5])\.([0-9]|[1-9][0-9]|1[0-9][0-9]|2[0-4][0-9]|25[0-5])\.([0-9]|[1-9][0-9]|1[0-9][0-9]|2[0-4][0-9]|25[0-5])"#).unwrap();
-    }
-    if IPV4_ADDRESS_RULE.is_match(a) && a != "127.0.0.1" {
-        return DoesNotMatch;
-    }
-    // The spec uses the phrase "if A is an IPv6 address", without giving specific instructions on
-    // how to tell if this is the case. In URLs, IPv6 addresses start with `[`, so let's go with that.
-    // See https://url.spec.whatwg.org/#host-parsing
-    if a.as_bytes()[0] == b'[' {
-        return DoesNotMatch;
+    match (Host::parse(a), b) {
+        (Ok(Host::Domain(ref a)), &Host::Domain(b)) if a.trim_end_matches('.') == b.trim_end_matches('.') => Matches,
+        // IP addresses only match if they are the IPv4 loopback address.
+        (Ok(Host::Ipv4(a)), &Host::Ipv4(b)) if a == b && a == Ipv4Addr::LOCALHOST => Matches,
+        _ => DoesNotMatch,
     }
-    Matches
 }
 
 /// https://www.w3.org/TR/CSP/#match-ports
-fn port_part_match(port_a: &str, port_b: &str, scheme_b: &str) -> MatchResult {
+///
+/// `port_a` does not include the leading `:`.
+fn port_part_match(port_a: &str, url: &Url, schemes: &SchemeRegistry) -> MatchResult {
+    let default_port = schemes.default_port(url.scheme());
+    // The url crate leaves out the port of special URLs when it is the default.
+    let port_b = url.port().or(default_port);
     if port_a.is_empty() {
-        if port_b == default_port_str(scheme_b) {
+        if port_b == default_port {
             return Matches;
         } else {
             return DoesNotMatch;
@@ -1353,17 +2173,10 @@ fn port_part_match(port_a: &str, port_b: &str, scheme_b: &str) -> MatchResult {
     if port_a == "*" {
         return Matches;
     }
-    if port_a == port_b {
-        return Matches;
-    }
-    if port_b == "" {
-        if port_a == default_port_str(scheme_b) {
-            return Matches;
-        } else {
-            return DoesNotMatch;
-        }
+    match port_a.parse::<u16>() {
+        Ok(port_a) if Some(port_a) == port_b => Matches,
+        _ => DoesNotMatch,
     }
-    DoesNotMatch
 }
 
 /// https://www.w3.org/TR/CSP/#match-paths
@@ -1384,7 +2197,7 @@ fn path_part_match(path_a: &str, path_b: &str) -> MatchResult {
         return DoesNotMatch;
     }
     if !exact_match {
-        debug_assert_eq!(&path_list_a[path_list_a.len()-1][..], "");
+        debug_assert_eq!(path_list_a[path_list_a.len()-1], "");
         path_list_a.pop();
     }
     let mut piece_b_iter = path_list_b.iter();
@@ -1399,59 +2212,26 @@ fn path_part_match(path_a: &str, path_b: &str) -> MatchResult {
     Matches
 }
 
-fn url_port(url: &Url) -> String {
-    match url.port() {
-        Some(num) => num.to_string(),
-        None => default_port_str(url.scheme()).to_string(),
-    }
-}
-
-fn default_port_str(scheme: &str) -> &'static str {
-    match scheme {
-        "ftp" => "21",
-        "gopher" => "70",
-        "http" => "80",
-        "https" => "443",
-        "ws" => "80",
-        "wss" => "443",
-        _ => "",
-    }
-}
-
-fn default_port(scheme: &str) -> Option<u16> {
-    Some(match scheme {
-        "ftp" => 21,
-        "gopher" => 70,
-        "http" => 80,
-        "https" => 443,
-        "ws" => 80,
-        "wss" => 443,
-        _ => return None,
-    })
-}
-
-fn origin_scheme_part_match(a: &Origin, b: &str) -> MatchResult {
-    if let &Origin::Tuple(ref scheme, ref _host, ref _port) = a {
-        scheme_part_match(&scheme[..], b)
+fn origin_scheme_part_match(a: &Origin, b: &str, schemes: &SchemeRegistry) -> MatchResult {
+    if let Origin::Tuple(scheme, _host, _port) = a {
+        scheme_part_match(&scheme[..], b, schemes)
     } else {
         DoesNotMatch
     }
 }
 
 /// https://www.w3.org/TR/CSP/#match-schemes
-fn scheme_part_match(a: &str, b: &str) -> MatchResult {
-    let a = a.to_ascii_lowercase();
-    let b = b.to_ascii_lowercase();
-    match (&a[..], &b[..]) {
-        _ if a == b => Matches,
-        ("http", "https") |
-        ("ws", "wss") |
-        ("wss", "https") => Matches,
-        _ => DoesNotMatch,
+fn scheme_part_match(a: &str, b: &str, schemes: &SchemeRegistry) -> MatchResult {
+    if schemes.does_scheme_match(a, b) {
+        Matches
+    } else {
+        DoesNotMatch
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+/// Hash algorithms, ordered from weakest to strongest.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum HashAlgorithm {
     Sha256,
     Sha384,
@@ -1468,31 +2248,123 @@ impl HashAlgorithm {
             _ => None,
         }
     }
-    pub fn apply(self, _value: &str) -> String {
-        unimplemented!();
+    /// Hash the UTF-8 encoding of `value`, and return the base64 encoding of the digest.
+    pub fn apply(self, value: &str) -> String {
+        self.apply_to_bytes(value.as_bytes())
+    }
+    /// Hash `bytes`, and return the base64 encoding of the digest.
+    pub fn apply_to_bytes(self, bytes: &[u8]) -> String {
+        use base64::Engine;
+        use sha2::Digest;
+        let digest = match self {
+            HashAlgorithm::Sha256 => sha2::Sha256::digest(bytes).to_vec(),
+            HashAlgorithm::Sha384 => sha2::Sha384::digest(bytes).to_vec(),
+            HashAlgorithm::Sha512 => sha2::Sha512::digest(bytes).to_vec(),
+        };
+        base64::engine::general_purpose::STANDARD.encode(digest)
     }
 }
 
 /// https://www.w3.org/TR/SRI/#integrity-metadata
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub struct HashFunction {
     algorithm: HashAlgorithm,
     value: String,
     // The spec defines a third member, options, but defines no values.
 }
 
+impl HashFunction {
+    pub fn algorithm(&self) -> HashAlgorithm {
+        self.algorithm
+    }
+    /// The expected digest, base64 encoded.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+    /// Whether the digest of `bytes` is this hash function's value.
+    ///
+    /// The base64url alphabet is accepted, as it is for hash-sources.
+    pub fn matches_bytes(&self, bytes: &[u8]) -> bool {
+        self.algorithm.apply_to_bytes(bytes) == self.value.replace('-', "+").replace('_', "/")
+    }
+}
+
 /// https://www.w3.org/TR/SRI/#parse-metadata
 #[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
 pub enum SubresourceIntegrityMetadata {
     NoMetadata,
     IntegritySources(Vec<HashFunction>)
 }
 
+impl SubresourceIntegrityMetadata {
+    /// Returns the hash functions that use the strongest algorithm present.
+    ///
+    /// https://www.w3.org/TR/SRI/#get-the-strongest-metadata
+    pub fn strongest_metadata(&self) -> Vec<&HashFunction> {
+        let sources = match *self {
+            SubresourceIntegrityMetadata::NoMetadata => return Vec::new(),
+            SubresourceIntegrityMetadata::IntegritySources(ref sources) => sources,
+        };
+        let strongest = match sources.iter().map(|source| source.algorithm).max() {
+            Some(strongest) => strongest,
+            None => return Vec::new(),
+        };
+        sources.iter().filter(|source| source.algorithm == strongest).collect()
+    }
+    /// https://www.w3.org/TR/SRI/#does-response-match-metadatalist
+    pub fn check_bytes(&self, bytes: &[u8]) -> IntegrityCheckResult {
+        let strongest = self.strongest_metadata();
+        let algorithm = match strongest.first() {
+            Some(source) => source.algorithm,
+            None => return IntegrityCheckResult::NoMetadata,
+        };
+        for source in &strongest {
+            if source.matches_bytes(bytes) {
+                return IntegrityCheckResult::Matches((*source).clone());
+            }
+        }
+        IntegrityCheckResult::DoesNotMatch {
+            expected: strongest.into_iter().cloned().collect(),
+            actual: HashFunction { algorithm, value: algorithm.apply_to_bytes(bytes) },
+        }
+    }
+}
+
+/// The result of checking a response body against integrity metadata.
+///
+/// https://www.w3.org/TR/SRI/#does-response-match-metadatalist
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub enum IntegrityCheckResult {
+    /// The metadata has no hash functions that this crate supports, so any body is allowed.
+    NoMetadata,
+    /// The body matches this hash function, which uses the strongest algorithm in the metadata.
+    Matches(HashFunction),
+    /// The body matches none of the hash functions that use the strongest algorithm in the metadata.
+    DoesNotMatch {
+        expected: Vec<HashFunction>,
+        /// The body's actual digest, with the strongest algorithm.
+        actual: HashFunction,
+    },
+}
+
+impl IntegrityCheckResult {
+    /// Whether the response should be used. A response that does not match is a network error.
+    pub fn is_allowed(&self) -> bool {
+        match *self {
+            IntegrityCheckResult::NoMetadata | IntegrityCheckResult::Matches(_) => true,
+            IntegrityCheckResult::DoesNotMatch { .. } => false,
+        }
+    }
+}
+
 lazy_static!{
     /// https://www.w3.org/TR/SRI/#the-integrity-attribute
-    /// This corresponds to the "hash-expression" grammar.
+    /// This corresponds to the "hash-with-options" grammar. Options are allowed, but ignored.
     static ref SUBRESOURCE_METADATA_GRAMMAR: Regex =
-        Regex::new(r#"(?P<algorithm>sha256|sha384|sha512)-(?P<value>[a-zA-Z0-9\+/\-_]+=*)"#).unwrap();
+        Regex::new(r#"^(?P<algorithm>sha256|sha384|sha512)-(?P<value>[a-zA-Z0-9\+/\-_]+=*)(\?[\x21-\x7E]*)?$"#).unwrap();
 }
 
 /// https://www.w3.org/TR/SRI/#parse-metadata
@@ -1514,6 +2386,18 @@ pub fn parse_subresource_integrity_metadata(string: &str) -> SubresourceIntegrit
     }
 }
 
+/**
+Checks a response body against the value of an `integrity` attribute.
+
+Only the hash functions that use the strongest algorithm in the metadata are checked,
+and the body is allowed if it matches any of them.
+
+https://www.w3.org/TR/SRI/#does-response-match-metadatalist
+*/
+pub fn does_response_match_metadata_list(body: &[u8], metadata: &str) -> IntegrityCheckResult {
+    parse_subresource_integrity_metadata(metadata).check_bytes(body)
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -0,0 +1,250 @@
+/*!
+A [tower] middleware that sets `Content-Security-Policy` headers on responses.
+
+This module is only available with the `tower` feature.
+
+```rust
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::middleware::CspLayer;
+# fn main() {
+let layer = CspLayer::new(Policy::parse(
+    "script-src 'strict-dynamic'; object-src 'none'",
+    PolicySource::Header,
+    PolicyDisposition::Enforce,
+));
+# let _ = layer;
+# }
+```
+
+Every request gets a fresh nonce, which handlers can read from the request's
+extensions as a `CspNonce`, to put in the `nonce` attribute of their `<script>` elements.
+
+[tower]: https://docs.rs/tower
+*/
+
+use base64::Engine;
+use http::{Request, Response, StatusCode};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+use CspList;
+use Directive;
+use PolicyDisposition;
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+use text_util::ascii_case_insensitive_match;
+use HASH_SOURCE_GRAMMAR;
+use NONCE_SOURCE_GRAMMAR;
+
+/// The nonce generated for a request, found in the request's extensions.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct CspNonce(pub String);
+
+/// The directives that `CspLayer::new` adds the request's nonce to.
+const NONCEABLE_DIRECTIVES: &[&str] = &["default-src", "script-src", "script-src-elem", "style-src", "style-src-elem"];
+
+fn generate_nonce() -> String {
+    let mut bytes = [0; 16];
+    getrandom::getrandom(&mut bytes).expect("failed to generate a CSP nonce");
+    base64::engine::general_purpose::STANDARD.encode(bytes)
+}
+
+fn contains_keyword(directive: &Directive, keyword: &str) -> bool {
+    directive.value.iter().any(|t| ascii_case_insensitive_match(t, keyword))
+}
+
+fn add_nonce(template: &CspList, nonce: &str) -> CspList {
+    let mut csp_list = template.clone();
+    for policy in &mut csp_list.0 {
+        for directive in &mut policy.directive_set {
+            if !NONCEABLE_DIRECTIVES.contains(&&directive.name[..]) || is_none(&directive.value) {
+                continue;
+            }
+            // A nonce disables `'unsafe-inline'`, which would break the page's inline scripts
+            // and styles. With `'strict-dynamic'`, `'unsafe-inline'` is only a fallback for
+            // older browsers, and the nonce is what allows scripts in newer ones.
+            if contains_keyword(directive, "'unsafe-inline'") && !contains_keyword(directive, "'strict-dynamic'") {
+                continue;
+            }
+            directive.value.push(format!("'nonce-{}'", nonce));
+        }
+    }
+    csp_list
+}
+
+fn is_none(value: &[String]) -> bool {
+    value.is_empty() || (value.len() == 1 && ascii_case_insensitive_match(&value[0], "'none'"))
+}
+
+/// Keywords, schemes and hosts are case-insensitive, but nonces and hashes are not.
+fn same_source_expression(a: &str, b: &str) -> bool {
+    a == b || (ascii_case_insensitive_match(a, b) &&
+        !NONCE_SOURCE_GRAMMAR.is_match(a) && !HASH_SOURCE_GRAMMAR.is_match(a))
+}
+
+/**
+Returns true if `upstream` does not allow anything that `required` does not.
+
+Every token of `upstream` has to be in `required`, so an upstream `sandbox` can only have
+fewer `allow-*` keywords, and an upstream source list can only have fewer source expressions,
+or be `'none'`.
+*/
+fn does_not_weaken(upstream: &Directive, required: &Directive) -> bool {
+    is_none(&upstream.value) ||
+        upstream.value.iter().all(|t| required.value.iter().any(|r| same_source_expression(t, r)))
+}
+
+/// Reporting directives do not restrict anything, so upstream may report anywhere.
+const REPORTING_DIRECTIVES: &[&str] = &["report-uri", "report-to"];
+
+/**
+Returns true if `upstream` enforces every directive in `baseline`, unweakened.
+
+Every enforced policy in a CSP list applies, so each baseline directive can be enforced by
+any of the upstream policies. This check is syntactic: an upstream `default-src 'none'`
+is stricter than a baseline `script-src 'self'`, but does not count, and neither does
+a source expression that only matches a subset of a baseline one.
+*/
+fn enforces_baseline(upstream: &CspList, baseline: &CspList) -> bool {
+    let enforced: Vec<&Directive> = upstream.0.iter()
+        .filter(|policy| policy.disposition == PolicyDisposition::Enforce)
+        .flat_map(|policy| policy.directive_set.iter())
+        .collect();
+    baseline.0.iter()
+        .flat_map(|policy| policy.directive_set.iter())
+        .filter(|required| !REPORTING_DIRECTIVES.contains(&&required.name[..]))
+        .all(|required| enforced.iter().any(|directive| {
+            directive.name == required.name && does_not_weaken(directive, required)
+        }))
+}
+
+/**
+A layer that sets CSP headers on every response.
+
+Policies with the `Report` disposition are sent as `Content-Security-Policy-Report-Only`.
+Headers that the inner service set are kept.
+*/
+#[derive(Clone)]
+pub struct CspLayer {
+    build: Arc<dyn Fn(&str) -> CspList + Send + Sync>,
+    baseline: Option<Arc<CspList>>,
+}
+
+impl fmt::Debug for CspLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("CspLayer").field("baseline", &self.baseline).finish()
+    }
+}
+
+impl CspLayer {
+    /**
+    Send `template` with every response, after adding `'nonce-...'` with the request's nonce
+    to its `default-src`, `script-src`, `script-src-elem`, `style-src` and `style-src-elem`
+    directives.
+
+    Directives that are `'none'` are left alone, and so are directives with `'unsafe-inline'`,
+    since a nonce would disable it, unless they also have `'strict-dynamic'`.
+    */
+    pub fn new<T: Into<CspList>>(template: T) -> CspLayer {
+        let template = template.into();
+        CspLayer::from_fn(move |nonce| add_nonce(&template, nonce))
+    }
+    /// Build the policies for every response by calling `build` with the request's nonce.
+    pub fn from_fn<F: Fn(&str) -> CspList + Send + Sync + 'static>(build: F) -> CspLayer {
+        CspLayer {
+            build: Arc::new(build),
+            baseline: None,
+        }
+    }
+    /**
+    Replace responses that do not already enforce every directive in `baseline`
+    with an empty `502 Bad Gateway` response.
+
+    This is meant for proxies, to refuse upstream responses with a weaker policy than required.
+    An upstream directive with the same name is enough if each of its tokens is also in the
+    baseline directive, so `script-src 'none'` satisfies a baseline of `script-src 'self'`,
+    but `script-src 'self' https:` does not. `report-uri` and `report-to` are not required.
+    */
+    pub fn require_upstream(mut self, baseline: CspList) -> CspLayer {
+        self.baseline = Some(Arc::new(baseline));
+        self
+    }
+}
+
+impl<S> Layer<S> for CspLayer {
+    type Service = CspService<S>;
+    fn layer(&self, inner: S) -> CspService<S> {
+        CspService {
+            inner,
+            layer: self.clone(),
+        }
+    }
+}
+
+/// The service returned by `CspLayer`.
+#[derive(Clone, Debug)]
+pub struct CspService<S> {
+    inner: S,
+    layer: CspLayer,
+}
+
+impl<S, ReqBody, ResBody> Service<Request<ReqBody>> for CspService<S>
+    where S: Service<Request<ReqBody>, Response = Response<ResBody>>, ResBody: Default {
+    type Response = Response<ResBody>;
+    type Error = S::Error;
+    type Future = ResponseFuture<S::Future>;
+    fn poll_ready(&mut self, cx: &mut Context) -> Poll<Result<(), S::Error>> {
+        self.inner.poll_ready(cx)
+    }
+    fn call(&mut self, mut request: Request<ReqBody>) -> Self::Future {
+        let nonce = generate_nonce();
+        let csp_list = (self.layer.build)(&nonce);
+        request.extensions_mut().insert(CspNonce(nonce));
+        ResponseFuture {
+            inner: self.inner.call(request),
+            csp_list,
+            baseline: self.layer.baseline.clone(),
+        }
+    }
+}
+
+pin_project! {
+    /// The future returned by `CspService`.
+    pub struct ResponseFuture<F> {
+        #[pin]
+        inner: F,
+        csp_list: CspList,
+        baseline: Option<Arc<CspList>>,
+    }
+}
+
+impl<F, ResBody, E> Future for ResponseFuture<F>
+    where F: Future<Output = Result<Response<ResBody>, E>>, ResBody: Default {
+    type Output = Result<Response<ResBody>, E>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        let this = self.project();
+        let mut response = match this.inner.poll(cx) {
+            Poll::Ready(Ok(response)) => response,
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+            Poll::Pending => return Poll::Pending,
+        };
+        if let Some(baseline) = this.baseline.as_ref() {
+            if !enforces_baseline(&CspList::from_header_map(response.headers()), baseline) {
+                response = Response::new(ResBody::default());
+                *response.status_mut() = StatusCode::BAD_GATEWAY;
+            }
+        }
+        // A policy that cannot be sent in a header is left out, rather than failing the response.
+        for policy in &this.csp_list.0 {
+            if let Ok(values) = CspList(vec![policy.clone()]).to_header_values() {
+                response.headers_mut().extend(values);
+            }
+        }
+        Poll::Ready(Ok(response))
+    }
+}
@@ -0,0 +1,184 @@
+/*!
+Turning a host-allowlist policy into a [strict CSP].
+
+Allowlists can usually be bypassed, through JSONP endpoints, script gadgets, or any other
+script that an allowlisted host serves. A strict policy allows scripts with nonces and
+hashes instead, and uses `'strict-dynamic'` to let those scripts load others.
+
+`migrate_to_strict` rewrites a policy's script directives into this form, keeping the
+original source expressions after `'strict-dynamic'`. Browsers that support it ignore
+them, but CSP2 browsers fall back to them, so the candidate policy still works there.
+It also sets `object-src 'none'` and `base-uri 'none'`, and lists the changes that the
+page needs before the candidate policy can be deployed.
+
+The candidate policy has no nonce. It is meant to be used as the template of a
+`CspLayer`, which adds a fresh nonce to every response. Otherwise, add a
+`'nonce-...'` source to its `script-src` yourself, for every response.
+
+```rust
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::migration::{self, RequiredChange};
+fn main() {
+    let policy = Policy::parse("script-src 'self' https://cdn.notriddle.com", PolicySource::Header, PolicyDisposition::Enforce);
+    let migration = migration::migrate_to_strict(&policy);
+    assert_eq!(
+        migration.policy.to_string(),
+        "script-src 'strict-dynamic' 'self' https://cdn.notriddle.com ;object-src 'none' ;base-uri 'none' ",
+    );
+    assert!(migration.required_changes.contains(&RequiredChange::RemoveBaseElements));
+}
+```
+
+[strict CSP]: https://web.dev/articles/strict-csp
+*/
+
+use browser_profile::BrowserProfile;
+use text_util::ascii_case_insensitive_match;
+use AllowResult;
+use Directive;
+use InlineCheckType;
+use Policy;
+use SourceList;
+use HOST_SOURCE_GRAMMAR;
+use SCHEME_SOURCE_GRAMMAR;
+
+/// A change to a page that is needed before it can use the policy from `migrate_to_strict`.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub enum RequiredChange {
+    /// Add the nonce to every inline `<script>` element.
+    /// The original policy allowed them with `'unsafe-inline'`.
+    NonceInlineScripts,
+    /// Move inline event handlers and `javascript:` URLs into nonced scripts, or allow
+    /// each one with a hash-source and `'unsafe-hashes'`.
+    /// The original policy allowed them with `'unsafe-inline'`.
+    MoveInlineEventHandlers,
+    /// Add the nonce to every `<script src>` element that loads from one of these source
+    /// expressions. `'strict-dynamic'` makes browsers ignore them, but scripts that nonced
+    /// scripts load are still allowed.
+    NonceExternalScripts(Vec<String>),
+    /// Stop using `eval()` and similar functions, which the original policy did not restrict.
+    RemoveEval,
+    /// Remove `<object>` and `<embed>` elements, which the original policy allowed.
+    RemovePlugins,
+    /// Remove `<base>` elements, which the original policy allowed.
+    RemoveBaseElements,
+}
+
+/// The result of `migrate_to_strict`.
+#[derive(Clone, Debug)]
+pub struct Migration {
+    /// The strict candidate policy, without a nonce.
+    pub policy: Policy,
+    /// What has to change in the page before `policy` can be enforced, in no particular order.
+    pub required_changes: Vec<RequiredChange>,
+}
+
+/// The source list that CSP2 browsers use instead of a strict policy that does not restrict
+/// scripts at all. It lets them load scripts from anywhere, like the original policy did.
+const UNRESTRICTED_FALLBACK: &[&str] = &["'unsafe-inline'", "https:", "http:"];
+
+/**
+Builds a strict candidate policy that is equivalent to `policy` in browsers without
+`'strict-dynamic'`, and lists the changes that the page needs for it.
+
+Script directives that are `'none'` are left alone, since they are already strict.
+`script-src-elem` and `script-src-attr` are migrated on their own, whatever `script-src` is:
+`script-src-elem` gets `'strict-dynamic'` like `script-src`, and a `script-src-attr` that
+allows every inline event handler becomes `'none'`. Browsers without these directives
+ignore them, so this does not change how CSP2 browsers treat the page.
+*/
+pub fn migrate_to_strict(policy: &Policy) -> Migration {
+    let mut required_changes = Vec::new();
+    let mut migrated = policy.clone();
+    let directive_value = |name: &str| policy.directive_set.iter().find(|d| d.name == name).map(|d| &d.value[..]);
+    let script_value = directive_value("script-src").or_else(|| directive_value("default-src"));
+    let script_value: Vec<String> = match script_value {
+        Some(value) => value.to_vec(),
+        None => {
+            required_changes.push(RequiredChange::RemoveEval);
+            UNRESTRICTED_FALLBACK.iter().map(|&s| s.to_owned()).collect()
+        }
+    };
+    if !is_none(&script_value) {
+        set_directive(&mut migrated, "script-src", strict_value(&script_value));
+    }
+    // In CSP3, these take precedence over `script-src` for elements and for attributes.
+    let element_value = directive_value("script-src-elem");
+    let attribute_value = directive_value("script-src-attr");
+    if let Some(value) = element_value {
+        if !is_none(value) {
+            set_directive(&mut migrated, "script-src-elem", strict_value(value));
+        }
+    }
+    if let Some(value) = attribute_value {
+        if allows_inline_event_handlers(value) {
+            set_directive(&mut migrated, "script-src-attr", vec!["'none'".to_owned()]);
+        }
+    }
+    add_script_changes(
+        element_value.unwrap_or(&script_value),
+        attribute_value.unwrap_or(&script_value),
+        &mut required_changes,
+    );
+    let object_value = directive_value("object-src").or_else(|| directive_value("default-src"));
+    if !object_value.map(is_none).unwrap_or(false) {
+        required_changes.push(RequiredChange::RemovePlugins);
+    }
+    set_directive(&mut migrated, "object-src", vec!["'none'".to_owned()]);
+    if !directive_value("base-uri").map(is_none).unwrap_or(false) {
+        required_changes.push(RequiredChange::RemoveBaseElements);
+    }
+    set_directive(&mut migrated, "base-uri", vec!["'none'".to_owned()]);
+    Migration { policy: migrated, required_changes }
+}
+
+fn is_none(value: &[String]) -> bool {
+    value.len() == 1 && ascii_case_insensitive_match(&value[0], "'none'")
+}
+
+/// `'strict-dynamic'`, followed by the original source expressions as a fallback.
+fn strict_value(value: &[String]) -> Vec<String> {
+    let mut strict = vec!["'strict-dynamic'".to_owned()];
+    strict.extend(value.iter()
+        .filter(|ex| !ascii_case_insensitive_match(ex, "'strict-dynamic'"))
+        .cloned());
+    strict
+}
+
+fn allows_inline_event_handlers(value: &[String]) -> bool {
+    SourceList(value).does_a_source_list_allow_all_inline_behavior_for_type(InlineCheckType::ScriptAttribute, &BrowserProfile::CSP3) == AllowResult::Allows
+}
+
+/// Lists the changes needed for the directives that govern script elements and inline event
+/// handlers in CSP3, once they are migrated. Directives that are `'none'` are not migrated.
+fn add_script_changes(element_value: &[String], attribute_value: &[String], required_changes: &mut Vec<RequiredChange>) {
+    if SourceList(element_value).does_a_source_list_allow_all_inline_behavior_for_type(InlineCheckType::Script, &BrowserProfile::CSP3) == AllowResult::Allows {
+        required_changes.push(RequiredChange::NonceInlineScripts);
+    }
+    if allows_inline_event_handlers(attribute_value) {
+        required_changes.push(RequiredChange::MoveInlineEventHandlers);
+    }
+    // With `'strict-dynamic'`, the allowlist is already ignored.
+    if is_none(element_value) || element_value.iter().any(|ex| ascii_case_insensitive_match(ex, "'strict-dynamic'")) {
+        return;
+    }
+    let allowlist: Vec<String> = element_value.iter()
+        .filter(|ex| HOST_SOURCE_GRAMMAR.is_match(ex) || SCHEME_SOURCE_GRAMMAR.is_match(ex) || ascii_case_insensitive_match(ex, "'self'"))
+        .cloned()
+        .collect();
+    if !allowlist.is_empty() {
+        required_changes.push(RequiredChange::NonceExternalScripts(allowlist));
+    }
+}
+
+/// Replaces the value of the directive called `name`, or adds it to the end of the policy.
+fn set_directive(policy: &mut Policy, name: &str, value: Vec<String>) {
+    match policy.directive_set.iter_mut().find(|d| d.name == name) {
+        Some(directive) => directive.value = value,
+        None => policy.directive_set.push(Directive {
+            name: name.to_owned(),
+            value,
+        }),
+    }
+}
@@ -0,0 +1,121 @@
+/*!
+Policy containers, which carry a document's or worker's policies, and the rules for when
+they are inherited from the document or worker that created them.
+
+https://html.spec.whatwg.org/multipage/browsers.html#policy-containers
+*/
+
+use CspList;
+use Response;
+use Url;
+#[cfg(feature = "serde")] use serde::{Deserialize, Serialize};
+
+/**
+A policy container holds the policies of a document or worker.
+
+To clone a policy container for inheritance, use `Clone::clone`.
+Policies are plain data, so this produces the deep copy that the spec requires.
+
+https://html.spec.whatwg.org/multipage/browsers.html#policy-container
+*/
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Deserialize, Serialize))]
+pub struct PolicyContainer {
+    pub csp_list: CspList,
+}
+
+impl PolicyContainer {
+    pub fn new(csp_list: CspList) -> PolicyContainer {
+        PolicyContainer { csp_list }
+    }
+    /**
+    If the response's URL is a `blob:` URL, pass the policy container of the environment
+    that created the blob in `blob_url_entry_policy_container`. If it is `None`,
+    for example because the blob URL was revoked, an empty policy container is returned.
+
+    https://html.spec.whatwg.org/multipage/browsers.html#creating-a-policy-container-from-a-fetch-response
+    */
+    pub fn create_from_fetch_response(
+        response: &Response,
+        blob_url_entry_policy_container: Option<&PolicyContainer>,
+    ) -> PolicyContainer {
+        if response.url.scheme() == "blob" {
+            return blob_url_entry_policy_container.cloned().unwrap_or_default();
+        }
+        PolicyContainer {
+            csp_list: response.csp_list.clone(),
+        }
+    }
+}
+
+/// https://fetch.spec.whatwg.org/#is-local
+pub fn url_is_local(url: &Url) -> bool {
+    matches!(url.scheme(), "about" | "blob" | "data")
+}
+
+/// https://html.spec.whatwg.org/multipage/browsers.html#requires-storing-the-policy-container-in-history
+pub fn requires_storing_the_policy_container_in_history(url: &Url) -> bool {
+    url.scheme() != "blob" && url_is_local(url)
+}
+
+/**
+Decides which policy container a document created by navigation gets.
+
+* `history_policy_container` is the policy container stored in the session history entry,
+  if the navigation is a history traversal that has one.
+* `initiator_policy_container` belongs to the document that started the navigation, if any.
+* `parent_policy_container` belongs to the parent document, if the navigable is a child navigable.
+* `response_policy_container` is the result of `PolicyContainer::create_from_fetch_response`,
+  if there was a response.
+
+HTML asserts that a stored history policy container only comes with a URL that
+`requires_storing_the_policy_container_in_history`, and that `about:srcdoc` always has a parent.
+Here, a history policy container for any other URL is ignored, so the document gets the
+policy container of its response, and `about:srcdoc` without a parent is treated like any
+other local URL.
+
+https://html.spec.whatwg.org/multipage/browsers.html#determining-navigation-params-policy-container
+*/
+pub fn determine_navigation_params_policy_container(
+    response_url: &Url,
+    history_policy_container: Option<&PolicyContainer>,
+    initiator_policy_container: Option<&PolicyContainer>,
+    parent_policy_container: Option<&PolicyContainer>,
+    response_policy_container: Option<PolicyContainer>,
+) -> PolicyContainer {
+    if let Some(history_policy_container) = history_policy_container {
+        if requires_storing_the_policy_container_in_history(response_url) {
+            return history_policy_container.clone();
+        }
+    }
+    if response_url.as_str() == "about:srcdoc" {
+        if let Some(parent_policy_container) = parent_policy_container {
+            return parent_policy_container.clone();
+        }
+    }
+    if url_is_local(response_url) {
+        if let Some(initiator_policy_container) = initiator_policy_container {
+            return initiator_policy_container.clone();
+        }
+    }
+    response_policy_container.unwrap_or_default()
+}
+
+/**
+Decides which policy container a worker gets.
+
+`owner_policy_container` belongs to the document or worker that created this one.
+`blob_url_entry_policy_container` is handled as in `PolicyContainer::create_from_fetch_response`.
+
+https://html.spec.whatwg.org/multipage/workers.html#initialize-worker-policy-container
+*/
+pub fn initialize_a_worker_global_scopes_policy_container(
+    response: &Response,
+    owner_policy_container: &PolicyContainer,
+    blob_url_entry_policy_container: Option<&PolicyContainer>,
+) -> PolicyContainer {
+    if url_is_local(&response.url) && response.url.scheme() != "blob" {
+        return owner_policy_container.clone();
+    }
+    PolicyContainer::create_from_fetch_response(response, blob_url_entry_policy_container)
+}
@@ -0,0 +1,458 @@
+/*!
+A [tower] service that receives CSP violation reports, for use as a `report-uri` or `report-to`
+endpoint.
+
+This module is only available with the `report-collector` feature.
+
+Both report formats that browsers send are accepted:
+
+* `application/csp-report`, sent for the `report-uri` directive.
+* `application/reports+json`, sent by the Reporting API for the `report-to` directive.
+  Reports of types other than `csp-violation` are ignored.
+
+Duplicate reports are dropped, and the number of reports forwarded to the sink is rate-limited,
+because any page that loads the policy can send reports.
+
+[tower]: https://docs.rs/tower
+*/
+
+use http::{Method, Request, Response, StatusCode};
+use http::header::CONTENT_TYPE;
+use http_body::Body;
+use http_body_util::{BodyExt, LengthLimitError, Limited};
+use http_body_util::combinators::Collect;
+use serde::{Deserialize, Serialize};
+use serde_json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower_service::Service;
+use PolicyDisposition;
+
+/**
+A CSP violation report, in either format.
+
+URLs are kept as strings, because browsers send values like `inline` and `eval`
+in place of a blocked URL.
+
+https://www.w3.org/TR/CSP/#reporting
+*/
+#[derive(Clone, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub struct CspReport {
+    pub document_url: String,
+    pub referrer: Option<String>,
+    pub blocked_url: Option<String>,
+    pub effective_directive: String,
+    pub original_policy: String,
+    pub source_file: Option<String>,
+    pub sample: Option<String>,
+    pub disposition: PolicyDisposition,
+    pub status_code: u16,
+    pub line_number: Option<u32>,
+    pub column_number: Option<u32>,
+}
+
+/// Where `ReportCollector` sends the reports that it accepts.
+pub trait ReportSink: Send + Sync + 'static {
+    fn report(&self, report: CspReport);
+}
+
+impl<F: Fn(CspReport) + Send + Sync + 'static> ReportSink for F {
+    fn report(&self, report: CspReport) {
+        self(report)
+    }
+}
+
+/// https://www.w3.org/TR/CSP/#deprecated-serialize-violation
+#[derive(Deserialize)]
+struct LegacyReportBody {
+    #[serde(rename = "csp-report")]
+    csp_report: LegacyReport,
+}
+
+#[derive(Deserialize)]
+struct LegacyReport {
+    #[serde(rename = "document-uri")]
+    document_uri: String,
+    referrer: Option<String>,
+    #[serde(rename = "blocked-uri")]
+    blocked_uri: Option<String>,
+    #[serde(rename = "effective-directive")]
+    effective_directive: Option<String>,
+    #[serde(rename = "violated-directive")]
+    violated_directive: Option<String>,
+    #[serde(rename = "original-policy", default)]
+    original_policy: String,
+    #[serde(rename = "source-file")]
+    source_file: Option<String>,
+    #[serde(rename = "script-sample")]
+    script_sample: Option<String>,
+    disposition: Option<String>,
+    #[serde(rename = "status-code", default)]
+    status_code: u16,
+    #[serde(rename = "line-number")]
+    line_number: Option<u32>,
+    #[serde(rename = "column-number")]
+    column_number: Option<u32>,
+}
+
+/// https://w3c.github.io/reporting/#serialize-reports
+#[derive(Deserialize)]
+struct Report {
+    #[serde(rename = "type")]
+    type_: String,
+    body: serde_json::Value,
+}
+
+/// https://www.w3.org/TR/CSP/#dictdef-cspviolationreportbody
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CspViolationReportBody {
+    #[serde(rename = "documentURL")]
+    document_url: String,
+    referrer: Option<String>,
+    #[serde(rename = "blockedURL")]
+    blocked_url: Option<String>,
+    effective_directive: String,
+    #[serde(default)]
+    original_policy: String,
+    source_file: Option<String>,
+    sample: Option<String>,
+    disposition: Option<String>,
+    #[serde(default)]
+    status_code: u16,
+    line_number: Option<u32>,
+    column_number: Option<u32>,
+}
+
+fn parse_disposition(disposition: Option<&str>) -> PolicyDisposition {
+    match disposition {
+        Some("report") => PolicyDisposition::Report,
+        _ => PolicyDisposition::Enforce,
+    }
+}
+
+fn non_empty(string: Option<String>) -> Option<String> {
+    string.filter(|s| !s.is_empty())
+}
+
+impl CspReport {
+    /// Parse the body of an `application/csp-report` request.
+    pub fn parse_csp_report(body: &[u8]) -> Result<CspReport, serde_json::Error> {
+        let report = serde_json::from_slice::<LegacyReportBody>(body)?.csp_report;
+        Ok(CspReport {
+            document_url: report.document_uri,
+            referrer: non_empty(report.referrer),
+            blocked_url: non_empty(report.blocked_uri),
+            effective_directive: report.effective_directive.or(report.violated_directive).unwrap_or_default(),
+            original_policy: report.original_policy,
+            source_file: non_empty(report.source_file),
+            sample: non_empty(report.script_sample),
+            disposition: parse_disposition(report.disposition.as_ref().map(|d| &d[..])),
+            status_code: report.status_code,
+            line_number: report.line_number,
+            column_number: report.column_number,
+        })
+    }
+    /**
+    Parse the body of an `application/reports+json` request.
+
+    Reports of other types are skipped.
+    */
+    pub fn parse_reports_json(body: &[u8]) -> Result<Vec<CspReport>, serde_json::Error> {
+        let mut reports = Vec::new();
+        for report in serde_json::from_slice::<Vec<Report>>(body)? {
+            if report.type_ != "csp-violation" {
+                continue;
+            }
+            let body: CspViolationReportBody = serde_json::from_value(report.body)?;
+            reports.push(CspReport {
+                document_url: body.document_url,
+                referrer: non_empty(body.referrer),
+                blocked_url: non_empty(body.blocked_url),
+                effective_directive: body.effective_directive,
+                original_policy: body.original_policy,
+                source_file: non_empty(body.source_file),
+                sample: non_empty(body.sample),
+                disposition: parse_disposition(body.disposition.as_ref().map(|d| &d[..])),
+                status_code: body.status_code,
+                line_number: body.line_number,
+                column_number: body.column_number,
+            });
+        }
+        Ok(reports)
+    }
+}
+
+struct RateLimitState {
+    /// When each report was last forwarded to the sink.
+    seen: HashMap<CspReport, Instant>,
+    window_start: Instant,
+    forwarded_in_window: usize,
+}
+
+struct Inner {
+    path: String,
+    sink: Box<dyn ReportSink>,
+    max_body_size: usize,
+    rate_limit: usize,
+    rate_limit_period: Duration,
+    dedup_period: Duration,
+    state: Mutex<RateLimitState>,
+}
+
+/// Reports that have been seen in the deduplication period are remembered, up to this many.
+const MAX_SEEN_REPORTS: usize = 10_000;
+
+impl Inner {
+    /// Returns false if the report was dropped because of the rate limit.
+    fn accept(&self, report: CspReport, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(&seen) = state.seen.get(&report) {
+            if now.duration_since(seen) < self.dedup_period {
+                return true;
+            }
+        }
+        if now.duration_since(state.window_start) >= self.rate_limit_period {
+            state.window_start = now;
+            state.forwarded_in_window = 0;
+        }
+        if state.forwarded_in_window >= self.rate_limit {
+            return false;
+        }
+        state.forwarded_in_window += 1;
+        if state.seen.len() >= MAX_SEEN_REPORTS {
+            let dedup_period = self.dedup_period;
+            state.seen.retain(|_, &mut seen| now.duration_since(seen) < dedup_period);
+            if state.seen.len() >= MAX_SEEN_REPORTS {
+                state.seen.clear();
+            }
+        }
+        state.seen.insert(report.clone(), now);
+        drop(state);
+        self.sink.report(report);
+        true
+    }
+    fn handle(&self, content_type: Option<&str>, body: &[u8]) -> StatusCode {
+        let reports = match content_type {
+            Some("application/csp-report") => CspReport::parse_csp_report(body).map(|r| vec![r]),
+            Some("application/reports+json") => CspReport::parse_reports_json(body),
+            // Some browsers label reports as plain JSON.
+            Some("application/json") => CspReport::parse_csp_report(body).map(|r| vec![r])
+                .or_else(|_| CspReport::parse_reports_json(body)),
+            _ => return StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        };
+        let reports = match reports {
+            Ok(reports) => reports,
+            Err(_) => return StatusCode::BAD_REQUEST,
+        };
+        let now = Instant::now();
+        let mut status = StatusCode::NO_CONTENT;
+        for report in reports {
+            if !self.accept(report, now) {
+                status = StatusCode::TOO_MANY_REQUESTS;
+            }
+        }
+        status
+    }
+}
+
+/**
+A service that accepts violation reports POSTed to a path, and forwards them to a `ReportSink`.
+
+Requests to other paths get `404 Not Found`, so mount this service at its path in your router,
+or serve it on its own.
+*/
+#[derive(Clone)]
+pub struct ReportCollector {
+    inner: Arc<Inner>,
+}
+
+impl fmt::Debug for ReportCollector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReportCollector").field("path", &self.inner.path).finish()
+    }
+}
+
+impl ReportCollector {
+    /**
+    Accept reports at `path`, with the default limits of `ReportCollector::builder`.
+    */
+    pub fn new<S: ReportSink>(path: &str, sink: S) -> ReportCollector {
+        ReportCollector::builder(path, sink).build()
+    }
+    /**
+    Configure a collector that accepts reports at `path`.
+
+    By default, bodies are limited to 64KiB, at most 100 reports are forwarded per minute,
+    and a report that is the same as one forwarded in the last minute is dropped.
+    */
+    pub fn builder<S: ReportSink>(path: &str, sink: S) -> ReportCollectorBuilder {
+        ReportCollectorBuilder {
+            path: path.to_owned(),
+            sink: Box::new(sink),
+            max_body_size: 64 * 1024,
+            rate_limit: 100,
+            rate_limit_period: Duration::from_secs(60),
+            dedup_period: Duration::from_secs(60),
+        }
+    }
+}
+
+/**
+The configuration of a `ReportCollector`, made by `ReportCollector::builder`.
+
+The collector and all of its clones share the rate limit and the deduplicated reports,
+so they are configured here, before `build` makes the collector.
+*/
+pub struct ReportCollectorBuilder {
+    path: String,
+    sink: Box<dyn ReportSink>,
+    max_body_size: usize,
+    rate_limit: usize,
+    rate_limit_period: Duration,
+    dedup_period: Duration,
+}
+
+impl fmt::Debug for ReportCollectorBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ReportCollectorBuilder")
+            .field("path", &self.path)
+            .field("max_body_size", &self.max_body_size)
+            .field("rate_limit", &self.rate_limit)
+            .field("rate_limit_period", &self.rate_limit_period)
+            .field("dedup_period", &self.dedup_period)
+            .finish()
+    }
+}
+
+impl ReportCollectorBuilder {
+    /// Reject request bodies larger than `max_body_size` bytes with `413 Payload Too Large`.
+    pub fn max_body_size(mut self, max_body_size: usize) -> ReportCollectorBuilder {
+        self.max_body_size = max_body_size;
+        self
+    }
+    /**
+    Forward at most `count` reports every `period`.
+
+    Reports past the limit are dropped, and the request gets `429 Too Many Requests`.
+    */
+    pub fn rate_limit(mut self, count: usize, period: Duration) -> ReportCollectorBuilder {
+        self.rate_limit = count;
+        self.rate_limit_period = period;
+        self
+    }
+    /// Drop reports that are the same as one forwarded less than `period` ago.
+    pub fn dedup_period(mut self, period: Duration) -> ReportCollectorBuilder {
+        self.dedup_period = period;
+        self
+    }
+    pub fn build(self) -> ReportCollector {
+        ReportCollector {
+            inner: Arc::new(Inner {
+                path: self.path,
+                sink: self.sink,
+                max_body_size: self.max_body_size,
+                rate_limit: self.rate_limit,
+                rate_limit_period: self.rate_limit_period,
+                dedup_period: self.dedup_period,
+                state: Mutex::new(RateLimitState {
+                    seen: HashMap::new(),
+                    window_start: Instant::now(),
+                    forwarded_in_window: 0,
+                }),
+            }),
+        }
+    }
+}
+
+fn empty_response(status: StatusCode) -> Response<String> {
+    let mut response = Response::new(String::new());
+    *response.status_mut() = status;
+    response
+}
+
+impl<B> Service<Request<B>> for ReportCollector
+    where B: Body, B::Error: Into<Box<dyn Error + Send + Sync>> {
+    type Response = Response<String>;
+    type Error = ::std::convert::Infallible;
+    type Future = ReportFuture<B>;
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: Request<B>) -> ReportFuture<B> {
+        let status = if request.uri().path() != self.inner.path {
+            Some(StatusCode::NOT_FOUND)
+        } else if request.method() != Method::POST {
+            Some(StatusCode::METHOD_NOT_ALLOWED)
+        } else {
+            None
+        };
+        if let Some(status) = status {
+            return ReportFuture {
+                state: ReportFutureState::Ready { response: Some(empty_response(status)) },
+            };
+        }
+        let content_type = request.headers().get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.split(';').next().unwrap_or("").trim().to_ascii_lowercase());
+        ReportFuture {
+            state: ReportFutureState::Collecting {
+                collect: Limited::new(request.into_body(), self.inner.max_body_size).collect(),
+                content_type,
+                inner: self.inner.clone(),
+            },
+        }
+    }
+}
+
+pin_project! {
+    /// The future returned by `ReportCollector`.
+    pub struct ReportFuture<B> where B: Body, B::Error: Into<Box<dyn Error + Send + Sync>> {
+        #[pin]
+        state: ReportFutureState<B>,
+    }
+}
+
+pin_project! {
+    #[project = ReportFutureStateProj]
+    enum ReportFutureState<B> where B: Body, B::Error: Into<Box<dyn Error + Send + Sync>> {
+        Ready {
+            response: Option<Response<String>>,
+        },
+        Collecting {
+            #[pin]
+            collect: Collect<Limited<B>>,
+            content_type: Option<String>,
+            inner: Arc<Inner>,
+        },
+    }
+}
+
+impl<B> Future for ReportFuture<B>
+    where B: Body, B::Error: Into<Box<dyn Error + Send + Sync>> {
+    type Output = Result<Response<String>, ::std::convert::Infallible>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            ReportFutureStateProj::Ready { response } => {
+                Poll::Ready(Ok(response.take().expect("polled after completion")))
+            }
+            ReportFutureStateProj::Collecting { collect, content_type, inner } => {
+                let status = match collect.poll(cx) {
+                    Poll::Pending => return Poll::Pending,
+                    Poll::Ready(Ok(collected)) => {
+                        inner.handle(content_type.as_ref().map(|c| &c[..]), &collected.to_bytes())
+                    }
+                    Poll::Ready(Err(ref error)) if error.is::<LengthLimitError>() => StatusCode::PAYLOAD_TOO_LARGE,
+                    Poll::Ready(Err(_)) => StatusCode::BAD_REQUEST,
+                };
+                Poll::Ready(Ok(empty_response(status)))
+            }
+        }
+    }
+}
@@ -18,41 +18,196 @@ bitflags!{
         const SANDBOXED_MODALS_FLAG = 0x00002000;
         const SANDBOXED_ORIENTATION_LOCK_BROWSING_CONTEXT_FLAG = 0x00004000;
         const SANDBOXED_PRESENTATION_BROWSING_CONTEXT_FLAG = 0x00008000;
+        const SANDBOXED_DOWNLOADS_BROWSING_CONTEXT_FLAG = 0x00010000;
+        const SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG = 0x00020000;
+        const SANDBOXED_STORAGE_ACCESS_BY_USER_ACTIVATION_FLAG = 0x00040000;
     }
 }
 
+/// The name of every flag, in the order of their bits.
+#[cfg(feature = "serde")]
+const SANDBOXING_FLAG_NAMES: &[(&str, SandboxingFlagSet)] = &[
+    ("SANDBOXED_NAVIGATION_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_NAVIGATION_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_AUXILIARY_NAVIGATION_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_AUXILIARY_NAVIGATION_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_TOP_LEVEL_NAVIGATION_WITHOUT_USER_ACTIVATION_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITHOUT_USER_ACTIVATION_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_TOP_LEVEL_NAVIGATION_WITH_USER_ACTIVATION_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITH_USER_ACTIVATION_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_PLUGINS_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_PLUGINS_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_POINTER_LOCK_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_POINTER_LOCK_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_AUTOMATIC_FEATURES_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_AUTOMATIC_FEATURES_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_STORAGE_AREA_URLS_FLAG",
+        SandboxingFlagSet::SANDBOXED_STORAGE_AREA_URLS_FLAG),
+    ("SANDBOXED_DOCUMENT_DOMAIN_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_DOCUMENT_DOMAIN_BROWSING_CONTEXT_FLAG),
+    ("SANDBOX_PROPOGATES_TO_AUXILIARY_BROWSING_CONTEXTS_FLAG",
+        SandboxingFlagSet::SANDBOX_PROPOGATES_TO_AUXILIARY_BROWSING_CONTEXTS_FLAG),
+    ("SANDBOXED_MODALS_FLAG",
+        SandboxingFlagSet::SANDBOXED_MODALS_FLAG),
+    ("SANDBOXED_ORIENTATION_LOCK_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_ORIENTATION_LOCK_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_PRESENTATION_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_PRESENTATION_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_DOWNLOADS_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_DOWNLOADS_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG",
+        SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG),
+    ("SANDBOXED_STORAGE_ACCESS_BY_USER_ACTIVATION_FLAG",
+        SandboxingFlagSet::SANDBOXED_STORAGE_ACCESS_BY_USER_ACTIVATION_FLAG),
+];
+
+/// Flag sets are serialized as the list of the names of their flags, in the order of their bits,
+/// so that recordings stay readable and do not depend on the bit values.
+#[cfg(feature = "serde")]
+impl ::serde::Serialize for SandboxingFlagSet {
+    fn serialize<S: ::serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(SANDBOXING_FLAG_NAMES.iter()
+            .filter(|&&(_, flag)| self.contains(flag))
+            .map(|&(name, _)| name))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> ::serde::Deserialize<'de> for SandboxingFlagSet {
+    fn deserialize<D: ::serde::Deserializer<'de>>(deserializer: D) -> Result<SandboxingFlagSet, D::Error> {
+        use serde::de::Error;
+        let mut flags = SandboxingFlagSet::empty();
+        for name in Vec::<String>::deserialize(deserializer)? {
+            let flag = SANDBOXING_FLAG_NAMES.iter()
+                .find(|&&(flag_name, _)| flag_name == name)
+                .ok_or_else(|| D::Error::custom(format!("unknown sandboxing flag {}", name)))?;
+            flags.insert(flag.1);
+        }
+        Ok(flags)
+    }
+}
+
+/// The keywords of the `sandbox` attribute and directive, in canonical order,
+/// with the flags that each of them removes.
+///
+/// `allow-top-navigation` sorts before the keywords whose flags it also removes,
+/// so serialization picks the shortest token list.
+const SANDBOXING_KEYWORDS: &[(&str, SandboxingFlagSet)] = &[
+    ("allow-downloads",
+        SandboxingFlagSet::SANDBOXED_DOWNLOADS_BROWSING_CONTEXT_FLAG),
+    ("allow-forms",
+        SandboxingFlagSet::SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG),
+    ("allow-modals",
+        SandboxingFlagSet::SANDBOXED_MODALS_FLAG),
+    ("allow-orientation-lock",
+        SandboxingFlagSet::SANDBOXED_ORIENTATION_LOCK_BROWSING_CONTEXT_FLAG),
+    ("allow-pointer-lock",
+        SandboxingFlagSet::SANDBOXED_POINTER_LOCK_BROWSING_CONTEXT_FLAG),
+    ("allow-popups",
+        SandboxingFlagSet::from_bits_truncate(
+            SandboxingFlagSet::SANDBOXED_AUXILIARY_NAVIGATION_BROWSING_CONTEXT_FLAG.bits() |
+                SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG.bits())),
+    ("allow-popups-to-escape-sandbox",
+        SandboxingFlagSet::SANDBOX_PROPOGATES_TO_AUXILIARY_BROWSING_CONTEXTS_FLAG),
+    ("allow-presentation",
+        SandboxingFlagSet::SANDBOXED_PRESENTATION_BROWSING_CONTEXT_FLAG),
+    ("allow-same-origin",
+        SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG),
+    ("allow-scripts",
+        SandboxingFlagSet::from_bits_truncate(
+            SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG.bits() |
+                SandboxingFlagSet::SANDBOXED_AUTOMATIC_FEATURES_BROWSING_CONTEXT_FLAG.bits())),
+    ("allow-storage-access-by-user-activation",
+        SandboxingFlagSet::SANDBOXED_STORAGE_ACCESS_BY_USER_ACTIVATION_FLAG),
+    ("allow-top-navigation",
+        SandboxingFlagSet::from_bits_truncate(
+            SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITHOUT_USER_ACTIVATION_BROWSING_CONTEXT_FLAG.bits() |
+                SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITH_USER_ACTIVATION_BROWSING_CONTEXT_FLAG.bits() |
+                SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG.bits())),
+    ("allow-top-navigation-by-user-activation",
+        SandboxingFlagSet::from_bits_truncate(
+            SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITH_USER_ACTIVATION_BROWSING_CONTEXT_FLAG.bits() |
+                SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG.bits())),
+    ("allow-top-navigation-to-custom-protocols",
+        SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG),
+];
+
+impl SandboxingFlagSet {
+    /**
+    Serialize this flag set as the list of keywords that would produce it
+    when passed to `parse_a_sandboxing_directive`.
+
+    Flags that no keyword can remove, like the sandboxed navigation browsing context flag,
+    are always set by the parser, so the result of parsing these tokens may contain more flags
+    than `self` does.
+    */
+    pub fn to_tokens(self) -> Vec<&'static str> {
+        let mut tokens = Vec::new();
+        let mut removed = SandboxingFlagSet::empty();
+        for &(keyword, flags) in SANDBOXING_KEYWORDS {
+            if !self.intersects(flags) && !removed.contains(flags) {
+                tokens.push(keyword);
+                removed.insert(flags);
+            }
+        }
+        tokens
+    }
+}
+
+/// https://html.spec.whatwg.org/multipage/browsers.html#parse-a-sandboxing-directive
 pub fn parse_a_sandboxing_directive(tokens: &[String]) -> SandboxingFlagSet {
+    parse_a_sandboxing_directive_with_diagnostics(tokens).0
+}
+
+/**
+Same as `parse_a_sandboxing_directive`, but also returns every token that is not a known keyword.
+
+https://html.spec.whatwg.org/multipage/browsers.html#parse-a-sandboxing-directive
+*/
+pub fn parse_a_sandboxing_directive_with_diagnostics(tokens: &[String]) -> (SandboxingFlagSet, Vec<&str>) {
     let mut output = SandboxingFlagSet::all();
+    let mut unknown_tokens = Vec::new();
     for token in tokens {
-        let remove = match &token[..] {
-            "allow-popups" =>
-                SandboxingFlagSet::SANDBOXED_AUXILIARY_NAVIGATION_BROWSING_CONTEXT_FLAG,
-            "allow-top-navigation" =>
-                SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITHOUT_USER_ACTIVATION_BROWSING_CONTEXT_FLAG |
-                    SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITH_USER_ACTIVATION_BROWSING_CONTEXT_FLAG,
-            "allow-top-navigation-by-user-activation" =>
-                SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITH_USER_ACTIVATION_BROWSING_CONTEXT_FLAG,
-            "allow-same-origin" =>
-                SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG,
-            "allow-forms" =>
-                SandboxingFlagSet::SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG,
-            "allow-pointer-lock" =>
-                SandboxingFlagSet::SANDBOXED_POINTER_LOCK_BROWSING_CONTEXT_FLAG,
-            "allow-scripts" =>
-                SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG |
-                    SandboxingFlagSet::SANDBOXED_AUTOMATIC_FEATURES_BROWSING_CONTEXT_FLAG,
-            "allow-popups-to-escape-sandbox" =>
-                SandboxingFlagSet::SANDBOX_PROPOGATES_TO_AUXILIARY_BROWSING_CONTEXTS_FLAG,
-            "allow-modals" =>
-                SandboxingFlagSet::SANDBOXED_MODALS_FLAG,
-            "allow-orientation-lock" =>
-                SandboxingFlagSet::SANDBOXED_ORIENTATION_LOCK_BROWSING_CONTEXT_FLAG,
-            "allow-presentation" =>
-                SandboxingFlagSet::SANDBOXED_PRESENTATION_BROWSING_CONTEXT_FLAG,
-            _ =>
-                SandboxingFlagSet::empty(),
-        };
-        output.remove(remove);
+        let keyword = SANDBOXING_KEYWORDS.iter()
+            .find(|&&(keyword, _)| token.eq_ignore_ascii_case(keyword));
+        match keyword {
+            Some(&(_, remove)) => output.remove(remove),
+            None => unknown_tokens.push(&token[..]),
+        }
+    }
+    (output, unknown_tokens)
+}
+
+/**
+The sandboxing flags that an `iframe` (or similar element) imposes on its content.
+*/
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(::serde::Deserialize, ::serde::Serialize))]
+pub struct EmbedderSandboxingFlags {
+    /// The result of parsing the element's `sandbox` attribute, or an empty set if it has none.
+    pub iframe_sandboxing_flag_set: SandboxingFlagSet,
+    /// The active sandboxing flag set of the element's node document.
+    pub node_document_active_sandboxing_flag_set: SandboxingFlagSet,
+}
+
+/**
+Pass `None` for `embedder` when creating a popup or a top-level browsing context.
+
+https://html.spec.whatwg.org/multipage/browsers.html#determining-the-creation-sandboxing-flags
+*/
+pub fn determine_the_creation_sandboxing_flags(
+    popup_sandboxing_flag_set: SandboxingFlagSet,
+    embedder: Option<EmbedderSandboxingFlags>,
+) -> SandboxingFlagSet {
+    match embedder {
+        None => popup_sandboxing_flag_set,
+        Some(embedder) =>
+            embedder.iframe_sandboxing_flag_set | embedder.node_document_active_sandboxing_flag_set,
     }
-    output
 }
@@ -0,0 +1,132 @@
+/*!
+The URL schemes that source expressions know about.
+
+The CSP matching algorithms treat some schemes specially: `*` only matches network schemes,
+`'self'` matches secure schemes from other origins on the same host, `http:` also matches
+`https:` URLs, and omitted ports are compared against each scheme's default port.
+The spec hard-codes these rules for `http`, `https`, `ws` and `wss`.
+
+Embedders that define their own schemes can describe them with a `SchemeRegistry`, and
+check policies with it through `RequestCheckOptions::scheme_registry`.
+
+```rust
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::scheme_registry::{SchemeProperties, SchemeRegistry};
+fn main() {
+    let mut registry = SchemeRegistry::default();
+    registry.register("app", SchemeProperties {
+        network: true,
+        secure: true,
+        default_port: Some(443),
+        upgrade_from: Vec::new(),
+    });
+    let options = RequestCheckOptions::default().scheme_registry(registry);
+    let csp_list = CspList::parse("script-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    let request = Request {
+        url: Url::parse("app://shell/index.js").unwrap(),
+        origin: Url::parse("https://www.notriddle.com").unwrap().origin(),
+        redirect_count: 0,
+        destination: Destination::Script,
+        initiator: Initiator::None,
+        nonce: String::new(),
+        integrity_metadata: String::new(),
+        parser_metadata: ParserMetadata::None,
+    };
+    assert_eq!(csp_list.should_request_be_blocked(&request).0, CheckResult::Blocked);
+    assert_eq!(csp_list.should_request_be_blocked_with_options(&request, &options).0, CheckResult::Allowed);
+}
+```
+*/
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// How the matching algorithms treat one URL scheme.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SchemeProperties {
+    /// Whether the `*` source expression matches URLs with this scheme.
+    pub network: bool,
+    /// Whether `'self'` matches URLs with this scheme on the same host as an origin with a
+    /// different scheme, like it does for `https` and `wss`.
+    pub secure: bool,
+    /// The port that URLs with this scheme use when they do not have one.
+    pub default_port: Option<u16>,
+    /// Schemes whose source expressions also match URLs with this scheme.
+    /// For example, `https` can be upgraded from `http`, so `http:` matches `https:` URLs.
+    ///
+    /// If this scheme is not secure, `'self'` on an origin with this scheme also matches URLs
+    /// with these schemes on the same host, like `'self'` on an `http` origin matches `ws` URLs.
+    pub upgrade_from: Vec<String>,
+}
+
+/// A set of URL schemes and their `SchemeProperties`.
+///
+/// `SchemeRegistry::default()` contains the schemes that the spec knows about.
+/// Schemes that are not registered are not network schemes, are not secure, have no default
+/// port, and are only matched by source expressions with the same scheme.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SchemeRegistry {
+    schemes: HashMap<String, SchemeProperties>,
+}
+
+lazy_static!{
+    static ref DEFAULT: Arc<SchemeRegistry> = Arc::new(SchemeRegistry::default());
+}
+
+/// `SchemeRegistry::default()`, shared by every `RequestCheckOptions::default()`.
+pub(crate) fn shared_default() -> Arc<SchemeRegistry> {
+    DEFAULT.clone()
+}
+
+impl Default for SchemeRegistry {
+    fn default() -> SchemeRegistry {
+        let mut registry = SchemeRegistry::empty();
+        let upgrade_from = |schemes: &[&str]| schemes.iter().map(|&s| s.to_owned()).collect();
+        registry.register("ftp", SchemeProperties { network: true, secure: false, default_port: Some(21), upgrade_from: Vec::new() });
+        registry.register("gopher", SchemeProperties { network: false, secure: false, default_port: Some(70), upgrade_from: Vec::new() });
+        registry.register("http", SchemeProperties { network: true, secure: false, default_port: Some(80), upgrade_from: upgrade_from(&["ws"]) });
+        registry.register("https", SchemeProperties { network: true, secure: true, default_port: Some(443), upgrade_from: upgrade_from(&["http", "ws", "wss"]) });
+        registry.register("ws", SchemeProperties { network: false, secure: false, default_port: Some(80), upgrade_from: Vec::new() });
+        registry.register("wss", SchemeProperties { network: false, secure: true, default_port: Some(443), upgrade_from: upgrade_from(&["ws"]) });
+        registry
+    }
+}
+
+impl SchemeRegistry {
+    /// A registry with no schemes in it, not even `http`.
+    pub fn empty() -> SchemeRegistry {
+        SchemeRegistry { schemes: HashMap::new() }
+    }
+    /// Adds a scheme, or replaces the properties of one that is already registered.
+    ///
+    /// Scheme names are ASCII case-insensitive.
+    pub fn register(&mut self, scheme: &str, properties: SchemeProperties) -> &mut SchemeRegistry {
+        let mut properties = properties;
+        for upgrade_from in &mut properties.upgrade_from {
+            upgrade_from.make_ascii_lowercase();
+        }
+        self.schemes.insert(scheme.to_ascii_lowercase(), properties);
+        self
+    }
+    pub fn get(&self, scheme: &str) -> Option<&SchemeProperties> {
+        self.schemes.get(&scheme.to_ascii_lowercase())
+    }
+    pub fn is_network(&self, scheme: &str) -> bool {
+        self.get(scheme).map(|p| p.network).unwrap_or(false)
+    }
+    pub fn is_secure(&self, scheme: &str) -> bool {
+        self.get(scheme).map(|p| p.secure).unwrap_or(false)
+    }
+    pub fn default_port(&self, scheme: &str) -> Option<u16> {
+        self.get(scheme).and_then(|p| p.default_port)
+    }
+    /// Whether a source expression with scheme `a` matches a URL with scheme `b`.
+    ///
+    /// https://www.w3.org/TR/CSP/#match-schemes
+    pub fn does_scheme_match(&self, a: &str, b: &str) -> bool {
+        let a = a.to_ascii_lowercase();
+        a.eq_ignore_ascii_case(b) ||
+            self.get(b).map(|p| p.upgrade_from.contains(&a)).unwrap_or(false)
+    }
+}
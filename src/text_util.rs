@@ -19,7 +19,7 @@ pub(crate) struct SplitAsciiWhitespace<'a>(&'a str);
 impl<'a> Iterator for SplitAsciiWhitespace<'a> {
     type Item = &'a str;
     fn next(&mut self) -> Option<Self::Item> {
-        self.0 = self.0.trim_left_matches(is_char_ascii_whitespace);
+        self.0 = self.0.trim_start_matches(is_char_ascii_whitespace);
         let mut s = self.0.splitn(2, is_char_ascii_whitespace);
         let next = s.next().unwrap_or("");
         self.0 = s.next().unwrap_or("");
@@ -31,7 +31,7 @@ impl<'a> Iterator for SplitAsciiWhitespace<'a> {
     }
 }
 
-pub(crate) fn split_ascii_whitespace(string: &str) -> SplitAsciiWhitespace {
+pub(crate) fn split_ascii_whitespace(string: &str) -> SplitAsciiWhitespace<'_> {
     SplitAsciiWhitespace(string)
 }
 
@@ -51,10 +51,10 @@ impl<'a> Iterator for SplitCommas<'a> {
     }
 }
 
-pub(crate) fn split_commas(string: &str) -> SplitCommas {
+pub(crate) fn split_commas(string: &str) -> SplitCommas<'_> {
     SplitCommas(string)
 }
 
 pub(crate) fn ascii_case_insensitive_match(a: &str, b: &str) -> bool {
-    a.to_ascii_lowercase() == b.to_ascii_lowercase()
+    a.eq_ignore_ascii_case(b)
 }
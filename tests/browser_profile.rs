@@ -0,0 +1,166 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::browser_profile::BrowserProfile;
+#[macro_use]
+mod common;
+use common::{csp_list, request};
+
+fn check_request(policy: &str, request: &Request, browser_profile: BrowserProfile) -> CheckResult {
+    let options = RequestCheckOptions::default().browser_profile(browser_profile);
+    csp_list(policy).should_request_be_blocked_with_options(request, &options).0
+}
+
+fn check_inline(policy: &str, nonce: Option<&str>, type_: InlineCheckType, source: &str, browser_profile: BrowserProfile) -> CheckResult {
+    let element = Element { nonce: nonce.map(|n| n.to_owned().into()) };
+    let options = RequestCheckOptions::default().browser_profile(browser_profile);
+    csp_list(policy).should_elements_inline_type_behavior_be_blocked_with_options(&element, type_, source, &options).0
+}
+
+#[test]
+fn default_profile_is_csp3() {
+    assert_eq!(BrowserProfile::default(), BrowserProfile::CSP3);
+    assert_eq!(RequestCheckOptions::default().browser_profile, BrowserProfile::CSP3);
+}
+
+#[test]
+fn nonces_disable_unsafe_inline_from_csp2() {
+    let policy = "script-src 'nonce-abc' 'unsafe-inline'";
+    assert_eq!(check_inline(policy, None, InlineCheckType::Script, "alert(1)", BrowserProfile::CSP1), CheckResult::Allowed);
+    assert_eq!(check_inline(policy, None, InlineCheckType::Script, "alert(1)", BrowserProfile::CSP2), CheckResult::Blocked);
+    assert_eq!(check_inline(policy, Some("abc"), InlineCheckType::Script, "alert(1)", BrowserProfile::CSP2), CheckResult::Allowed);
+}
+
+#[test]
+fn nonces_are_ignored_in_csp1() {
+    let policy = "script-src 'self' 'nonce-abc'";
+    assert_eq!(check_inline(policy, Some("abc"), InlineCheckType::Script, "alert(1)", BrowserProfile::CSP1), CheckResult::Blocked);
+    let mut request = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://cdn.notriddle.com/a.js", Destination::Script) };
+    request.nonce = "abc".to_owned();
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP1), CheckResult::Blocked);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP2), CheckResult::Allowed);
+}
+
+#[test]
+fn strict_dynamic_falls_back_to_the_allowlist() {
+    let policy = "script-src 'strict-dynamic' 'nonce-abc' https://cdn.notriddle.com";
+    let loaded = Request { parser_metadata: ParserMetadata::NotParserInserted, ..request("https://evil.example/a.js", Destination::Script) };
+    assert_eq!(check_request(policy, &loaded, BrowserProfile::CSP3), CheckResult::Allowed);
+    assert_eq!(check_request(policy, &loaded, BrowserProfile::CSP2), CheckResult::Blocked);
+    let allowlisted = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://cdn.notriddle.com/a.js", Destination::Script) };
+    assert_eq!(check_request(policy, &allowlisted, BrowserProfile::CSP3), CheckResult::Blocked);
+    assert_eq!(check_request(policy, &allowlisted, BrowserProfile::CSP2), CheckResult::Allowed);
+}
+
+#[test]
+fn strict_dynamic_without_hashes() {
+    let policy = "script-src 'strict-dynamic' 'nonce-abc'";
+    let parser_inserted = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://www.notriddle.com/a.js", Destination::Script) };
+    assert_eq!(check_request(policy, &parser_inserted, BrowserProfile::CSP3), CheckResult::Blocked);
+    let loaded = Request { parser_metadata: ParserMetadata::NotParserInserted, ..request("https://www.notriddle.com/a.js", Destination::Script) };
+    assert_eq!(check_request(policy, &loaded, BrowserProfile::CSP3), CheckResult::Allowed);
+}
+
+#[test]
+fn unsafe_inline_with_strict_dynamic_is_a_csp2_fallback() {
+    let policy = "script-src 'strict-dynamic' 'unsafe-inline'";
+    assert_eq!(check_inline(policy, None, InlineCheckType::Script, "alert(1)", BrowserProfile::CSP3), CheckResult::Blocked);
+    assert_eq!(check_inline(policy, None, InlineCheckType::Script, "alert(1)", BrowserProfile::CSP2), CheckResult::Allowed);
+}
+
+#[test]
+fn unsafe_hashes_is_csp3_only() {
+    let policy = "script-src 'unsafe-hashes' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='";
+    assert_eq!(check_inline(policy, None, InlineCheckType::ScriptAttribute, "alert(1)", BrowserProfile::CSP3), CheckResult::Allowed);
+    assert_eq!(check_inline(policy, None, InlineCheckType::ScriptAttribute, "alert(1)", BrowserProfile::CSP2), CheckResult::Blocked);
+}
+
+#[test]
+fn element_directives_fall_back_to_script_src() {
+    let policy = "script-src 'self'; script-src-elem https://cdn.notriddle.com";
+    let request = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://cdn.notriddle.com/a.js", Destination::Script) };
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP3), CheckResult::Allowed);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP2), CheckResult::Blocked);
+    let policy = "style-src 'unsafe-inline'; style-src-attr 'none'";
+    assert_eq!(check_inline(policy, None, InlineCheckType::StyleAttribute, "color: red", BrowserProfile::CSP3), CheckResult::Blocked);
+    assert_eq!(check_inline(policy, None, InlineCheckType::StyleAttribute, "color: red", BrowserProfile::CSP2), CheckResult::Allowed);
+}
+
+#[test]
+fn worker_fallback_lists() {
+    let policy = "script-src 'self'; child-src https://cdn.notriddle.com; worker-src 'none'";
+    let request = request("https://cdn.notriddle.com/worker.js", Destination::Worker);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP3), CheckResult::Blocked);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP2), CheckResult::Allowed);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP1), CheckResult::Blocked);
+}
+
+#[test]
+fn frame_fallback_lists() {
+    let policy = "default-src 'self'; child-src https://frames.notriddle.com";
+    let request = request("https://frames.notriddle.com/", Destination::IFrame);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP2), CheckResult::Allowed);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP1), CheckResult::Blocked);
+}
+
+#[test]
+fn external_script_hashes_are_csp3_only() {
+    let policy = "script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='";
+    let mut request = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://cdn.notriddle.com/a.js", Destination::Script) };
+    request.integrity_metadata = "sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=".to_owned();
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP3), CheckResult::Allowed);
+    assert_eq!(check_request(policy, &request, BrowserProfile::CSP2), CheckResult::Blocked);
+}
+
+#[test]
+fn post_request_check_uses_the_profile() {
+    let policy = "script-src 'strict-dynamic' 'nonce-abc'";
+    let request = Request { parser_metadata: ParserMetadata::NotParserInserted, ..request("https://evil.example/a.js", Destination::Script) };
+    let response = Response {
+        url: request.url.clone(),
+        redirect_count: 0,
+        csp_list: CspList(Vec::new()),
+    };
+    let csp_list = csp_list(policy);
+    let csp2 = RequestCheckOptions::default().browser_profile(BrowserProfile::CSP2);
+    assert_eq!(csp_list.should_response_to_request_be_blocked(&request, &response).0, CheckResult::Allowed);
+    assert_eq!(csp_list.should_response_to_request_be_blocked_with_options(&request, &response, &csp2).0, CheckResult::Blocked);
+}
+
+#[test]
+fn violations_name_directives_the_profile_supports() {
+    let csp2 = RequestCheckOptions::default().browser_profile(BrowserProfile::CSP2);
+    let script = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://evil.example/a.js", Destination::Script) };
+    let (_, violations) = csp_list("default-src 'self'").should_request_be_blocked_with_options(&script, &csp2);
+    assert_eq!(violations[0].effective_directive, "script-src");
+    let (_, violations) = csp_list("default-src 'self'").should_request_be_blocked(&script);
+    assert_eq!(violations[0].effective_directive, "script-src-elem");
+    let worker = request("https://evil.example/w.js", Destination::Worker);
+    let (_, violations) = csp_list("child-src 'self'").should_request_be_blocked_with_options(&worker, &csp2);
+    assert_eq!(violations[0].effective_directive, "child-src");
+    let element = Element { nonce: None };
+    let (_, violations) = csp_list("script-src 'self'").should_elements_inline_type_behavior_be_blocked_with_options(
+        &element, InlineCheckType::ScriptAttribute, "alert(1)", &csp2);
+    assert_eq!(violations[0].effective_directive, "script-src");
+}
+
+#[test]
+fn profiles_are_built_from_the_constants() {
+    let mut profile = BrowserProfile::CSP3;
+    profile.strict_dynamic = false;
+    let options = RequestCheckOptions::default().browser_profile(profile);
+    assert!(options.browser_profile.nonces_and_hashes);
+    assert_ne!(options.browser_profile, BrowserProfile::CSP3);
+}
+
+#[test]
+fn default_src_covers_what_older_profiles_have_no_directive_for() {
+    let script = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://evil.example/a.js", Destination::Script) };
+    let worker = request("https://evil.example/w.js", Destination::Worker);
+    for &profile in &[BrowserProfile::CSP1, BrowserProfile::CSP2] {
+        assert_eq!(check_request("default-src 'self'", &script, profile), CheckResult::Blocked);
+        assert_eq!(check_request("default-src 'self'", &worker, profile), CheckResult::Blocked);
+        assert_eq!(check_inline("default-src 'self'", None, InlineCheckType::Style, "color: red", profile), CheckResult::Blocked);
+    }
+    assert_eq!(check_request("child-src 'self'", &worker, BrowserProfile::CSP2), CheckResult::Blocked);
+    assert_eq!(check_request("child-src 'self'", &worker, BrowserProfile::CSP1), CheckResult::Allowed);
+}
@@ -0,0 +1,56 @@
+//! Helpers shared by the integration tests.
+//! Include them with `#[macro_use] mod common;`.
+
+#![allow(dead_code, unused_macros)]
+
+use content_security_policy::*;
+
+/// An enforced header policy.
+pub fn csp_list(policy: &str) -> CspList {
+    CspList::parse(policy, PolicySource::Header, PolicyDisposition::Enforce)
+}
+
+/// A request from `https://www.notriddle.com` without any metadata.
+///
+/// Tests that need metadata set it with struct update syntax:
+/// `Request { nonce: "abc".to_owned(), ..request(url, Destination::Script) }`.
+pub fn request(url: &str, destination: Destination) -> Request {
+    Request {
+        url: Url::parse(url).unwrap(),
+        origin: Url::parse("https://www.notriddle.com").unwrap().origin(),
+        redirect_count: 0,
+        destination,
+        initiator: Initiator::None,
+        nonce: String::new(),
+        integrity_metadata: String::new(),
+        parser_metadata: ParserMetadata::None,
+    }
+}
+
+/// Generates one test per row, each checking a request with `CspList::should_request_be_blocked_with_options`.
+///
+/// `initiator`, `nonce`, `integrity` and `options` are optional, and must come in that order.
+macro_rules! test_should_request_be_blocked {
+    (@or $default:expr;) => { $default };
+    (@or $default:expr; $value:expr) => { $value };
+    ($((name: $name:ident, url: $url:expr, origin: $origin:expr, policy: $policy:expr, dest: $destination:tt,
+        $(initiator: $initiator:tt,)* $(nonce: $nonce:expr,)* $(integrity: $integrity:expr,)* $(options: $options:expr,)*
+        result: $result:tt)),*$(,)*) => {
+        $(
+            #[test]
+            fn $name() {
+                let csp_list = CspList::parse($policy, PolicySource::Header, PolicyDisposition::Enforce);
+                let request = Request {
+                    origin: Url::parse($origin).unwrap().origin(),
+                    initiator: test_should_request_be_blocked!(@or Initiator::None; $(Initiator::$initiator)*),
+                    nonce: test_should_request_be_blocked!(@or ""; $($nonce)*).to_owned(),
+                    integrity_metadata: test_should_request_be_blocked!(@or ""; $($integrity)*).to_owned(),
+                    ..common::request($url, Destination::$destination)
+                };
+                let options = test_should_request_be_blocked!(@or RequestCheckOptions::default(); $($options)*);
+                let (check_result, _) = csp_list.should_request_be_blocked_with_options(&request, &options);
+                assert_eq!(check_result, CheckResult::$result);
+            }
+        )*
+    }
+}
@@ -0,0 +1,66 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::compatibility::{self, CompatibilityIssue, CspLevel, IssueKind};
+
+fn issues(policy: &str) -> Vec<CompatibilityIssue> {
+    let policy = Policy::parse(policy, PolicySource::Header, PolicyDisposition::Enforce);
+    compatibility::analyze(&policy).issues
+}
+
+fn issue(level: CspLevel, kind: IssueKind) -> CompatibilityIssue {
+    CompatibilityIssue { level, kind }
+}
+
+#[test]
+fn unsafe_inline_fallback_is_wide_open_in_csp1() {
+    assert_eq!(issues("script-src 'nonce-abc' 'unsafe-inline'"), vec![
+        issue(CspLevel::Csp1, IssueKind::InlineScriptsAllowed),
+    ]);
+}
+
+#[test]
+fn strict_dynamic_with_full_fallback() {
+    let policy = Policy::parse("script-src 'nonce-abc' 'strict-dynamic' 'unsafe-inline' https:", PolicySource::Header, PolicyDisposition::Enforce);
+    let report = compatibility::analyze(&policy);
+    assert_eq!(report.issues, vec![
+        issue(CspLevel::Csp1, IssueKind::InlineScriptsAllowed),
+        issue(CspLevel::Csp1, IssueKind::ScriptsFromAnyHostAllowed),
+        issue(CspLevel::Csp2, IssueKind::ScriptsFromAnyHostAllowed),
+    ]);
+    assert!(report.level(CspLevel::Csp3).is_protected());
+    assert!(!report.level(CspLevel::Csp2).is_protected());
+    assert!(!report.level(CspLevel::Csp2).inline_scripts);
+}
+
+#[test]
+fn strict_dynamic_without_host_fallback_breaks_in_csp1() {
+    assert_eq!(issues("script-src 'nonce-abc' 'strict-dynamic'"), vec![
+        issue(CspLevel::Csp1, IssueKind::ScriptsBlocked),
+    ]);
+}
+
+#[test]
+fn allowlist_policy_has_no_issues() {
+    assert_eq!(issues("script-src 'self' https://cdn.notriddle.com"), vec![]);
+    assert_eq!(issues("img-src *"), vec![]);
+}
+
+#[test]
+fn wildcard_host_source_is_any_host() {
+    let policy = Policy::parse("script-src 'strict-dynamic' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=' *", PolicySource::Header, PolicyDisposition::Enforce);
+    let report = compatibility::analyze(&policy);
+    assert!(report.level(CspLevel::Csp1).scripts_from_any_host);
+    assert!(!report.level(CspLevel::Csp3).scripts_from_any_host);
+}
+
+#[test]
+fn element_directives_are_csp3_only() {
+    let policy = Policy::parse("default-src 'self' 'unsafe-inline'; script-src-elem 'nonce-abc'", PolicySource::Header, PolicyDisposition::Enforce);
+    let report = compatibility::analyze(&policy);
+    assert_eq!(report.level(CspLevel::Csp3).directive, Some("script-src-elem"));
+    assert_eq!(report.level(CspLevel::Csp2).directive, Some("default-src"));
+    assert_eq!(report.issues, vec![
+        issue(CspLevel::Csp1, IssueKind::InlineScriptsAllowed),
+        issue(CspLevel::Csp2, IssueKind::InlineScriptsAllowed),
+    ]);
+}
@@ -0,0 +1,241 @@
+//! Runs the conformance cases in `tests/conformance/`.
+//! See `tests/conformance/README.md` for the file format.
+
+extern crate content_security_policy;
+use content_security_policy::*;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+struct WptCase {
+    file: String,
+    fields: BTreeMap<String, String>,
+}
+
+impl WptCase {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.fields.get(key).map(|v| &v[..])
+    }
+    fn required(&self, key: &str) -> &str {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("{}: case {:?} is missing `{}`", self.file, self.get("name"), key),
+        }
+    }
+    fn name(&self) -> &str {
+        self.required("name")
+    }
+    fn request(&self) -> Request {
+        Request {
+            url: Url::parse(self.required("url")).unwrap(),
+            origin: Url::parse(self.required("origin")).unwrap().origin(),
+            redirect_count: self.get("redirect-count").map(|c| c.parse().unwrap()).unwrap_or(0),
+            destination: parse_destination(self.required("destination")),
+            initiator: parse_initiator(self.get("initiator").unwrap_or("None")),
+            nonce: self.get("nonce").unwrap_or("").to_owned(),
+            integrity_metadata: self.get("integrity").unwrap_or("").to_owned(),
+            parser_metadata: parse_parser_metadata(self.get("parser").unwrap_or("None")),
+        }
+    }
+    fn expected(&self) -> CheckResult {
+        match self.required("result") {
+            "Allowed" => CheckResult::Allowed,
+            "Blocked" => CheckResult::Blocked,
+            result => panic!("{}: unknown result {}", self.file, result),
+        }
+    }
+    fn run(&self) -> CheckResult {
+        let csp_list = CspList::parse(self.required("policy"), PolicySource::Header, PolicyDisposition::Enforce);
+        match self.get("check").unwrap_or("request") {
+            "request" => csp_list.should_request_be_blocked(&self.request()).0,
+            "response" => {
+                let request = self.request();
+                let response = Response {
+                    csp_list: CspList::parse(self.get("response-policy").unwrap_or(""), PolicySource::Header, PolicyDisposition::Enforce),
+                    url: self.get("response-url").map(|url| Url::parse(url).unwrap()).unwrap_or_else(|| request.url.clone()),
+                    redirect_count: request.redirect_count,
+                };
+                csp_list.should_response_to_request_be_blocked(&request, &response).0
+            }
+            "inline" => {
+                let element = Element { nonce: self.get("element-nonce").map(Into::into) };
+                let type_ = parse_inline_check_type(self.required("inline-type"));
+                csp_list.should_elements_inline_type_behavior_be_blocked(&element, type_, self.get("inline-source").unwrap_or("")).0
+            }
+            check => panic!("{}: unknown check {}", self.file, check),
+        }
+    }
+}
+
+fn parse_destination(destination: &str) -> Destination {
+    use Destination::*;
+    match destination {
+        "None" => None,
+        "Audio" => Audio,
+        "AudioWorklet" => AudioWorklet,
+        "Document" => Document,
+        "Embed" => Embed,
+        "FencedFrame" => FencedFrame,
+        "Font" => Font,
+        "Frame" => Frame,
+        "IFrame" => IFrame,
+        "Image" => Image,
+        "Json" => Json,
+        "Manifest" => Manifest,
+        "Object" => Object,
+        "PaintWorklet" => PaintWorklet,
+        "Report" => Report,
+        "Script" => Script,
+        "ServiceWorker" => ServiceWorker,
+        "SharedWorker" => SharedWorker,
+        "SpeculationRules" => SpeculationRules,
+        "Style" => Style,
+        "Track" => Track,
+        "Video" => Video,
+        "WebIdentity" => WebIdentity,
+        "Worker" => Worker,
+        "Xslt" => Xslt,
+        _ => panic!("unknown destination {}", destination),
+    }
+}
+
+fn parse_initiator(initiator: &str) -> Initiator {
+    match initiator {
+        "Prefetch" => Initiator::Prefetch,
+        "Prerender" => Initiator::Prerender,
+        "Fetch" => Initiator::Fetch,
+        "None" => Initiator::None,
+        _ => panic!("unknown initiator {}", initiator),
+    }
+}
+
+fn parse_inline_check_type(type_: &str) -> InlineCheckType {
+    match type_ {
+        "Script" => InlineCheckType::Script,
+        "ScriptAttribute" => InlineCheckType::ScriptAttribute,
+        "Style" => InlineCheckType::Style,
+        "StyleAttribute" => InlineCheckType::StyleAttribute,
+        "Navigation" => InlineCheckType::Navigation,
+        "SpeculationRules" => InlineCheckType::SpeculationRules,
+        _ => panic!("unknown inline check type {}", type_),
+    }
+}
+
+fn parse_parser_metadata(parser_metadata: &str) -> ParserMetadata {
+    match parser_metadata {
+        "ParserInserted" => ParserMetadata::ParserInserted,
+        "NotParserInserted" => ParserMetadata::NotParserInserted,
+        "None" => ParserMetadata::None,
+        _ => panic!("unknown parser metadata {}", parser_metadata),
+    }
+}
+
+fn conformance_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance")
+}
+
+fn load_cases() -> Vec<WptCase> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(conformance_dir()).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map(|e| e == "txt").unwrap_or(false))
+        .filter(|path| path.file_name().unwrap() != "expected-failures.txt")
+        .collect();
+    paths.sort();
+    let mut cases = Vec::new();
+    for path in paths {
+        let file = path.file_name().unwrap().to_string_lossy().into_owned();
+        let contents = fs::read_to_string(&path).unwrap();
+        let mut fields = BTreeMap::new();
+        for line in contents.lines().chain(Some("")) {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            if line.is_empty() {
+                if !fields.is_empty() {
+                    cases.push(WptCase { file: file.clone(), fields });
+                    fields = BTreeMap::new();
+                }
+                continue;
+            }
+            let (key, value) = match line.find(':') {
+                Some(i) => (&line[..i], line[i+1..].trim()),
+                None => panic!("{}: malformed line {:?}", file, line),
+            };
+            fields.insert(key.to_owned(), value.to_owned());
+        }
+    }
+    cases
+}
+
+fn load_expected_failures() -> Vec<String> {
+    let contents = fs::read_to_string(conformance_dir().join("expected-failures.txt")).unwrap();
+    contents.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn conformance_cases() {
+    let cases = load_cases();
+    let expected_failures = load_expected_failures();
+    let mut names = HashSet::new();
+    let mut passed = 0;
+    let mut problems = Vec::new();
+    let mut known_failures = Vec::new();
+    for case in &cases {
+        assert!(names.insert(case.name().to_owned()), "duplicate case {}", case.name());
+        let result = case.run();
+        let pass = result == case.expected();
+        let listed = expected_failures.iter().any(|name| name == case.name());
+        if pass {
+            passed += 1;
+        } else if listed {
+            known_failures.push(format!("{}: {}", case.file, case.name()));
+        }
+        match (pass, listed) {
+            (false, false) => problems.push(format!(
+                "{}: {} fails (expected {:?}, got {:?})",
+                case.file,
+                case.name(),
+                case.expected(),
+                result,
+            )),
+            (true, true) => problems.push(format!(
+                "{}: {} passes, so remove it from expected-failures.txt",
+                case.file,
+                case.name(),
+            )),
+            _ => {}
+        }
+    }
+    let mut listed = HashSet::new();
+    for name in &expected_failures {
+        if !names.contains(name) {
+            problems.push(format!("expected-failures.txt lists {}, which is not a case", name));
+        } else if !listed.insert(name) {
+            problems.push(format!("expected-failures.txt lists {} twice", name));
+        }
+    }
+    let mut summary = format!(
+        "conformance: {}/{} cases pass, {} known failures\n",
+        passed,
+        cases.len(),
+        known_failures.len(),
+    );
+    for failure in &known_failures {
+        summary.push_str(&format!("conformance: known failure {}\n", failure));
+    }
+    // Written to the real standard error instead of through `eprint!`,
+    // which the test harness captures unless `--nocapture` is passed.
+    io::stderr().write_all(summary.as_bytes()).unwrap();
+    assert!(
+        problems.is_empty(),
+        "{}cases that do not match tests/conformance/:\n{}",
+        summary,
+        problems.join("\n"),
+    );
+}
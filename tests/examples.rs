@@ -1,27 +1,8 @@
 extern crate content_security_policy;
 use content_security_policy::*;
-
-macro_rules! test_should_request_be_blocked {
-    ($((name: $name:ident, url: $url:expr, origin: $origin:expr, policy: $policy:expr, dest: $destination:tt, result: $result:tt)),*$(,)*) => {
-        $(
-            #[test]
-            fn $name() {
-                let csp_list = CspList::parse($policy, PolicySource::Header, PolicyDisposition::Enforce);
-                let (check_result, _) = csp_list.should_request_be_blocked(&Request {
-                    url: Url::parse($url).unwrap(),
-                    origin: Url::parse($origin).unwrap().origin(),
-                    redirect_count: 0,
-                    destination: Destination::$destination,
-                    initiator: Initiator::None,
-                    nonce: String::new(),
-                    integrity_metadata: String::new(),
-                    parser_metadata: ParserMetadata::None,
-                });
-                assert_eq!(check_result, CheckResult::$result);
-            }
-        )*
-    }
-}
+#[macro_use]
+mod common;
+use common::request;
 
 // all tests should have a name starting with pre_request_
 test_should_request_be_blocked!{
@@ -350,6 +331,286 @@ test_should_request_be_blocked!{
         policy: "child-src 'none'",
         dest: Document,
         result: Blocked),
+    (   name: pre_request_default_frame_allow,
+        url: "https://www.notriddle2.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: Frame,
+        result: Allowed),
+    (   name: pre_request_default_frame_block,
+        url: "https://www.evil.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: Frame,
+        result: Blocked),
+    (   name: pre_request_default_iframe_allow,
+        url: "https://www.notriddle2.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: IFrame,
+        result: Allowed),
+    (   name: pre_request_default_iframe_block,
+        url: "https://www.evil.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: IFrame,
+        result: Blocked),
+    (   name: pre_request_default_fencedframe_allow,
+        url: "https://www.notriddle2.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: FencedFrame,
+        result: Allowed),
+    (   name: pre_request_default_fencedframe_block,
+        url: "https://www.evil.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: FencedFrame,
+        result: Blocked),
+    (   name: pre_request_default_json_allow,
+        url: "https://www.notriddle2.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: Json,
+        result: Allowed),
+    (   name: pre_request_default_json_block,
+        url: "https://www.evil.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: Json,
+        result: Blocked),
+    (   name: pre_request_default_webidentity_allow,
+        url: "https://www.notriddle2.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: WebIdentity,
+        result: Allowed),
+    (   name: pre_request_default_webidentity_block,
+        url: "https://www.evil.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: WebIdentity,
+        result: Blocked),
+    (   name: pre_request_default_speculationrules_allow,
+        url: "https://www.notriddle2.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: SpeculationRules,
+        result: Allowed),
+    (   name: pre_request_default_speculationrules_block,
+        url: "https://www.evil.com/child/",
+        origin: "https://www.notriddle.com",
+        policy: "default-src www.notriddle2.com",
+        dest: SpeculationRules,
+        result: Blocked),
+    (   name: pre_request_frame_src_iframe_block,
+        url: "https://www.evil.com/frame.html",
+        origin: "https://www.notriddle.com",
+        policy: "default-src *; frame-src 'self'",
+        dest: IFrame,
+        result: Blocked),
+    (   name: pre_request_child_src_frame_block,
+        url: "https://www.evil.com/frame.html",
+        origin: "https://www.notriddle.com",
+        policy: "child-src 'self'",
+        dest: Frame,
+        result: Blocked),
+    (   name: pre_request_frame_src_fencedframe_block,
+        url: "https://www.evil.com/ad.html",
+        origin: "https://www.notriddle.com",
+        policy: "frame-src 'self'",
+        dest: FencedFrame,
+        result: Blocked),
+    (   name: pre_request_fenced_frame_src_fencedframe_allow,
+        url: "https://www.evil.com/ad.html",
+        origin: "https://www.notriddle.com",
+        policy: "frame-src 'self'; fenced-frame-src https://www.evil.com",
+        dest: FencedFrame,
+        result: Allowed),
+    (   name: pre_request_fenced_frame_src_iframe_allow,
+        url: "https://www.evil.com/frame.html",
+        origin: "https://www.notriddle.com",
+        policy: "fenced-frame-src 'none'",
+        dest: IFrame,
+        result: Allowed),
+    (   name: pre_request_connect_src_json_block,
+        url: "https://www.evil.com/data.json",
+        origin: "https://www.notriddle.com",
+        policy: "connect-src 'self'; script-src *",
+        dest: Json,
+        result: Blocked),
+    (   name: pre_request_connect_src_webidentity_allow,
+        url: "https://idp.notriddle.com/.well-known/web-identity",
+        origin: "https://www.notriddle.com",
+        policy: "connect-src *.notriddle.com",
+        dest: WebIdentity,
+        result: Allowed),
+    (   name: pre_request_script_src_speculationrules_block,
+        url: "https://www.evil.com/rules.json",
+        origin: "https://www.notriddle.com",
+        policy: "script-src 'self'; connect-src *",
+        dest: SpeculationRules,
+        result: Blocked),
+    (   name: pre_request_script_src_elem_speculationrules_allow,
+        url: "https://www.notriddle.com/rules.json",
+        origin: "https://www.notriddle.com",
+        policy: "script-src 'none'; script-src-elem 'self'",
+        dest: SpeculationRules,
+        result: Allowed),
+    (   name: pre_request_host_unicode_policy_punycode_url,
+        url: "https://xn--mnchen-3ya.example/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src münchen.example",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_unicode_policy_unicode_url,
+        url: "https://münchen.example/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src MÜNCHEN.example",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_punycode_policy_unicode_url,
+        url: "https://münchen.example/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src xn--mnchen-3ya.example",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_unicode_wildcard,
+        url: "https://www.münchen.example/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *.münchen.example",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_unicode_block,
+        url: "https://munchen.example/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src münchen.example",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_mixed_case,
+        url: "https://WWW.NotRiddle.com/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src www.NOTRIDDLE.com",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_mixed_case_wildcard,
+        url: "https://Img.NotRiddle.com/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *.NOTRIDDLE.com",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_trailing_dot_url,
+        url: "https://www.notriddle.com./a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src www.notriddle.com",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_trailing_dot_policy,
+        url: "https://www.notriddle.com/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src www.notriddle.com.",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_trailing_dot_wildcard,
+        url: "https://img.notriddle.com./a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *.notriddle.com",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_wildcard_does_not_match_bare_domain,
+        url: "https://notriddle.com/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *.notriddle.com",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_wildcard_needs_label_boundary,
+        url: "https://evilnotriddle.com/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *.notriddle.com",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_ipv4_loopback,
+        url: "http://127.0.0.1/a.png",
+        origin: "http://127.0.0.1",
+        policy: "img-src 127.0.0.1",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_ipv4_other_block,
+        url: "http://192.168.0.1/a.png",
+        origin: "http://192.168.0.1",
+        policy: "img-src 192.168.0.1",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_ipv4_wildcard_block,
+        url: "http://10.0.0.1/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *.0.0.1",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_ipv4_localhost_block,
+        url: "http://127.0.0.1/a.png",
+        origin: "http://127.0.0.1",
+        policy: "img-src localhost",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_ipv6_block,
+        url: "http://[::1]/a.png",
+        origin: "http://[::1]",
+        policy: "img-src [::1]",
+        dest: Image,
+        result: Blocked),
+    (   name: pre_request_host_ipv6_self,
+        url: "http://[::1]/a.png",
+        origin: "http://[::1]",
+        policy: "img-src 'self'",
+        dest: Image,
+        result: Allowed),
+    (   name: pre_request_host_ipv6_star,
+        url: "http://[2001:db8::1]/a.png",
+        origin: "https://www.notriddle.com",
+        policy: "img-src *",
+        dest: Image,
+        result: Allowed),
+}
+
+test_should_request_be_blocked!{
+    (   name: pre_request_prefetch_uses_default_src_block,
+        url: "https://www.evil.com/script.js",
+        origin: "https://www.notriddle.com",
+        policy: "default-src 'self'; script-src *",
+        dest: Script,
+        initiator: Prefetch,
+        result: Blocked),
+    (   name: pre_request_prefetch_uses_default_src_allow,
+        url: "https://www.notriddle.com/script.js",
+        origin: "https://www.notriddle.com",
+        policy: "default-src 'self'; script-src *",
+        dest: Script,
+        initiator: Prefetch,
+        result: Allowed),
+    (   name: pre_request_prefetch_src_is_ignored,
+        url: "https://www.notriddle.com/script.js",
+        origin: "https://www.notriddle.com",
+        policy: "prefetch-src 'none'",
+        dest: Script,
+        initiator: Prefetch,
+        result: Allowed),
+    (   name: pre_request_speculation_rules_prerender_allow,
+        url: "https://www.notriddle.com/next",
+        origin: "https://www.notriddle.com",
+        policy: "default-src 'self'; frame-src *",
+        dest: Document,
+        initiator: Prerender,
+        result: Allowed),
+}
+
+#[test]
+fn pre_request_prefetch_violation_names_default_src() {
+    let csp_list = CspList::parse("default-src 'self'; script-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    let request = Request { initiator: Initiator::Prefetch, ..request("https://www.evil.com/script.js", Destination::Script) };
+    let (check_result, violations) = csp_list.should_request_be_blocked(&request);
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations[0].effective_directive, "default-src");
 }
 
 macro_rules! test_should_elements_inline_type_behavior_be_blocked {
@@ -383,4 +644,246 @@ test_should_elements_inline_type_behavior_be_blocked!{
         inline_check_type: Style,
         source: "",
         result: Allowed),
-}
\ No newline at end of file
+    (   name: inline_allowed_script_hash,
+        policy: "script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='",
+        nonce: None,
+        inline_check_type: Script,
+        source: "alert(1)",
+        result: Allowed),
+    (   name: inline_allowed_script_hash_base64url,
+        policy: "script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF-pI='",
+        nonce: None,
+        inline_check_type: Script,
+        source: "alert(1)",
+        result: Allowed),
+    (   name: inline_blocked_script_hash,
+        policy: "script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='",
+        nonce: None,
+        inline_check_type: Script,
+        source: "alert(2)",
+        result: Blocked),
+    (   name: inline_allowed_speculation_rules_keyword,
+        policy: "script-src 'self' 'inline-speculation-rules'",
+        nonce: None,
+        inline_check_type: SpeculationRules,
+        source: "{}",
+        result: Allowed),
+    (   name: inline_blocked_speculation_rules_without_keyword,
+        policy: "script-src 'self'",
+        nonce: None,
+        inline_check_type: SpeculationRules,
+        source: "{}",
+        result: Blocked),
+    (   name: inline_blocked_script_with_speculation_rules_keyword,
+        policy: "script-src 'inline-speculation-rules'",
+        nonce: None,
+        inline_check_type: Script,
+        source: "alert(1)",
+        result: Blocked),
+    (   name: inline_allowed_speculation_rules_nonce,
+        policy: "script-src 'nonce-abc'",
+        nonce: Some("abc".into()),
+        inline_check_type: SpeculationRules,
+        source: "{}",
+        result: Allowed),
+    (   name: inline_allowed_speculation_rules_unsafe_inline,
+        policy: "script-src-elem 'unsafe-inline'; script-src 'none'",
+        nonce: None,
+        inline_check_type: SpeculationRules,
+        source: "{}",
+        result: Allowed),
+    (   name: inline_blocked_speculation_rules_unsafe_inline_strict_dynamic,
+        policy: "script-src 'unsafe-inline' 'strict-dynamic'",
+        nonce: None,
+        inline_check_type: SpeculationRules,
+        source: "{}",
+        result: Blocked),
+    (   name: inline_allowed_speculation_rules_strict_dynamic_keyword,
+        policy: "script-src 'strict-dynamic' 'nonce-abc' 'inline-speculation-rules'",
+        nonce: None,
+        inline_check_type: SpeculationRules,
+        source: "{}",
+        result: Allowed),
+}
+
+#[test]
+fn inline_speculation_rules_effective_directive() {
+    let csp_list = CspList::parse("default-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, violations) = csp_list.should_elements_inline_type_behavior_be_blocked(
+        &Element { nonce: None },
+        InlineCheckType::SpeculationRules,
+        "{}",
+    );
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(&violations[0].effective_directive[..], "script-src-elem");
+}
+
+#[test]
+fn pre_request_speculation_rules_prerender() {
+    let csp_list = CspList::parse("default-src 'self'; frame-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    let request = Request { initiator: Initiator::Prerender, ..request("https://www.evil.com/next", Destination::Document) };
+    let (check_result, violations) = csp_list.should_request_be_blocked(&request);
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(&violations[0].effective_directive[..], "default-src");
+}
+
+#[test]
+fn inline_report_sample_is_truncated() {
+    let csp_list = CspList::parse("script-src 'none' 'report-sample'", PolicySource::Header, PolicyDisposition::Enforce);
+    let source = "é".repeat(50);
+    let (check_result, violations) = csp_list.should_elements_inline_type_behavior_be_blocked(
+        &Element { nonce: None },
+        InlineCheckType::Script,
+        &source,
+    );
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations.len(), 1);
+    match violations[0].resource {
+        ViolationResource::Inline { sample: Some(ref sample) } => assert_eq!(&sample[..], &"é".repeat(40)[..]),
+        ref resource => panic!("unexpected resource {:?}", resource),
+    }
+}
+
+#[test]
+fn inline_no_report_sample_no_sample() {
+    let csp_list = CspList::parse("script-src 'none'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (_, violations) = csp_list.should_elements_inline_type_behavior_be_blocked(
+        &Element { nonce: None },
+        InlineCheckType::Script,
+        "alert(1)",
+    );
+    assert_eq!(violations.len(), 1);
+    match violations[0].resource {
+        ViolationResource::Inline { sample: None } => {},
+        ref resource => panic!("unexpected resource {:?}", resource),
+    }
+}
+
+#[test]
+fn pre_request_violation_has_effective_directive() {
+    let mut csp_list = CspList::parse("img-src 'none'", PolicySource::Header, PolicyDisposition::Enforce);
+    csp_list.append(CspList::parse("default-src www.notriddle.com", PolicySource::Header, PolicyDisposition::Report));
+    let request = request("https://www.evil.com/script.js", Destination::Script);
+    let (check_result, violations) = csp_list.should_request_be_blocked(&request);
+    assert_eq!(check_result, CheckResult::Allowed);
+    assert!(violations.is_empty());
+    let violations = csp_list.report_violations_for_request(&request);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(&violations[0].effective_directive[..], "script-src-elem");
+    assert_eq!(violations[0].directive.to_string(), "default-src www.notriddle.com ");
+    assert_eq!(violations[0].policy.to_string(), "default-src www.notriddle.com ");
+    assert_eq!(violations[0].disposition, PolicyDisposition::Report);
+    assert_eq!(violations[0].status, 0);
+    assert!(violations[0].url.is_none());
+    assert!(violations[0].source_location.is_none());
+}
+
+#[test]
+fn response_violation_has_its_own_effective_directive() {
+    let csp_list = CspList::parse("sandbox", PolicySource::Header, PolicyDisposition::Enforce);
+    let request = request("https://www.notriddle.com/worker.js", Destination::Worker);
+    let response = Response {
+        url: request.url.clone(),
+        redirect_count: 0,
+        csp_list: csp_list.clone(),
+    };
+    let (check_result, violations) = CspList(vec![]).should_response_to_request_be_blocked(&request, &response);
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(&violations[0].effective_directive[..], "sandbox");
+}
+
+#[test]
+fn inline_violation_has_effective_directive() {
+    let csp_list = CspList::parse("default-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (_, violations) = csp_list.should_elements_inline_type_behavior_be_blocked(
+        &Element { nonce: None },
+        InlineCheckType::StyleAttribute,
+        "color: red",
+    );
+    assert_eq!(violations.len(), 1);
+    assert_eq!(&violations[0].effective_directive[..], "style-src-attr");
+    assert_eq!(violations[0].disposition, PolicyDisposition::Enforce);
+}
+
+#[test]
+fn inline_javascript_url_unsafe_hashes() {
+    let url = Url::parse("javascript:alert(%22hi%20there%22)").unwrap();
+    let element = Element { nonce: None };
+    let csp_list = CspList::parse("script-src 'unsafe-hashes' 'sha256-XYEpbXqtecPE6LH4BQEWp4/UQ1XikdwfGtzO0mHfkcE='", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, violations) = csp_list.should_javascript_url_navigation_be_blocked(&url, &element);
+    assert_eq!(check_result, CheckResult::Allowed);
+    assert!(violations.is_empty());
+    // Without 'unsafe-hashes', hash-sources do not apply to navigations.
+    let csp_list = CspList::parse("script-src 'sha256-XYEpbXqtecPE6LH4BQEWp4/UQ1XikdwfGtzO0mHfkcE='", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, _) = csp_list.should_javascript_url_navigation_be_blocked(&url, &element);
+    assert_eq!(check_result, CheckResult::Blocked);
+    // The hash is of the decoded script, not the URL.
+    let csp_list = CspList::parse("script-src 'unsafe-hashes' 'sha256-easYSpBOQpmlEDUTKW8VRZBjVlSFh8l0jkrmdPKggUQ='", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, _) = csp_list.should_javascript_url_navigation_be_blocked(&url, &element);
+    assert_eq!(check_result, CheckResult::Blocked);
+}
+
+#[test]
+fn inline_javascript_url_report_sample() {
+    let url = Url::parse("javascript:alert(%22hi%20there%22)").unwrap();
+    let csp_list = CspList::parse("script-src 'self' 'report-sample'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, violations) = csp_list.should_javascript_url_navigation_be_blocked(&url, &Element { nonce: None });
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(&violations[0].effective_directive[..], "script-src-elem");
+    match violations[0].resource {
+        ViolationResource::Inline { sample: Some(ref sample) } => assert_eq!(sample, "alert(\"hi there\")"),
+        ref resource => panic!("unexpected resource {:?}", resource),
+    }
+}
+
+#[test]
+fn inline_javascript_url_unsafe_inline() {
+    let url = Url::parse("javascript:void(0)").unwrap();
+    let csp_list = CspList::parse("script-src 'unsafe-inline'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, _) = csp_list.should_javascript_url_navigation_be_blocked(&url, &Element { nonce: None });
+    assert_eq!(check_result, CheckResult::Allowed);
+    let url = Url::parse("https://www.evil.com/").unwrap();
+    let csp_list = CspList::parse("script-src 'none'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, violations) = csp_list.should_javascript_url_navigation_be_blocked(&url, &Element { nonce: None });
+    assert_eq!(check_result, CheckResult::Allowed);
+    assert!(violations.is_empty());
+}
+
+#[test]
+fn webrtc_allowed_without_directive() {
+    let csp_list = CspList::parse("default-src 'none'", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(csp_list.is_webrtc_allowed());
+    assert!(csp_list.should_rtc_connections_be_blocked().1.is_empty());
+}
+
+#[test]
+fn webrtc_allow_and_block() {
+    let csp_list = CspList::parse("webrtc 'ALLOW'", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(csp_list.is_webrtc_allowed());
+    let csp_list = CspList::parse("webrtc 'block'", PolicySource::Header, PolicyDisposition::Enforce);
+    let (check_result, violations) = csp_list.should_rtc_connections_be_blocked();
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(&violations[0].effective_directive[..], "webrtc");
+    match violations[0].resource {
+        ViolationResource::None => {},
+        ref resource => panic!("unexpected resource {:?}", resource),
+    }
+    // Anything other than exactly 'allow' blocks.
+    let csp_list = CspList::parse("webrtc 'allow' 'block'", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(!csp_list.is_webrtc_allowed());
+    let csp_list = CspList::parse("webrtc", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(!csp_list.is_webrtc_allowed());
+}
+
+#[test]
+fn webrtc_report_only() {
+    let mut csp_list = CspList::parse("webrtc 'block'", PolicySource::Header, PolicyDisposition::Report);
+    csp_list.append(CspList::parse("webrtc 'allow'", PolicySource::Header, PolicyDisposition::Enforce));
+    let (check_result, violations) = csp_list.should_rtc_connections_be_blocked();
+    assert_eq!(check_result, CheckResult::Allowed);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].disposition, PolicyDisposition::Report);
+}
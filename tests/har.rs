@@ -0,0 +1,106 @@
+#![cfg(feature = "har")]
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::har::*;
+
+const EXAMPLE: &str = include_str!("har/example.har");
+
+#[test]
+fn har_parse_requests() {
+    let recorded = parse_har(EXAMPLE).unwrap();
+    let destinations: Vec<Destination> = recorded.iter().map(|r| r.request.destination).collect();
+    assert_eq!(destinations, [
+        Destination::Document,
+        Destination::Script,
+        Destination::Script,
+        Destination::Image,
+        Destination::Image,
+        Destination::Font,
+    ]);
+    let redirect_counts: Vec<u32> = recorded.iter().map(|r| r.request.redirect_count).collect();
+    assert_eq!(redirect_counts, [0, 0, 0, 0, 1, 0]);
+    let origin = Url::parse("https://www.example.com/").unwrap().origin();
+    assert!(recorded.iter().all(|r| r.request.origin == origin));
+    assert_eq!(recorded[0].response.csp_list.to_string(), "frame-ancestors 'none' ");
+    assert_eq!(recorded[4].response.redirect_count, 1);
+}
+
+#[test]
+fn har_replay_blocked_requests() {
+    let recorded = parse_har(EXAMPLE).unwrap();
+    let csp_list = CspList::parse("script-src 'self'; img-src 'self'; font-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    let blocked = replay(&recorded, &csp_list);
+    let summary: Vec<(BlockedStage, &str, &str)> = blocked.iter()
+        .map(|b| (b.stage, &b.violations[0].effective_directive[..], b.request.url.as_str()))
+        .collect();
+    assert_eq!(summary, [
+        (BlockedStage::Request, "script-src-elem", "https://cdn.example.net/lib.js"),
+        (BlockedStage::Request, "img-src", "https://images.example.net/logo.png"),
+    ]);
+    assert_eq!(blocked[0].violations[0].directive.to_string(), "script-src 'self' ");
+}
+
+#[test]
+fn har_replay_report_only_blocks_nothing() {
+    let recorded = parse_har(EXAMPLE).unwrap();
+    let csp_list = CspList::parse("default-src 'none'", PolicySource::Header, PolicyDisposition::Report);
+    assert!(replay(&recorded, &csp_list).is_empty());
+}
+
+#[test]
+fn har_errors() {
+    match parse_har("{\"log\": {}}") {
+        Err(HarError::Json(_)) => {}
+        result => panic!("unexpected result {:?}", result),
+    }
+    let bad_url = r#"{"log": {"entries": [{"request": {"url": "not a url"}, "response": {"status": 200}}]}}"#;
+    match parse_har(bad_url) {
+        Err(HarError::Url(ref url, _)) => assert_eq!(url, "not a url"),
+        result => panic!("unexpected result {:?}", result),
+    }
+    assert!(parse_har(r#"{"log": {"entries": []}}"#).unwrap().is_empty());
+}
+
+#[test]
+fn har_later_documents_are_iframes() {
+    let har = r#"{"log": {"entries": [
+        {"_resourceType": "document", "request": {"url": "https://www.example.com/"}, "response": {"status": 200}},
+        {"_resourceType": "document", "request": {"url": "https://ads.example.net/frame.html"}, "response": {"status": 200}}
+    ]}}"#;
+    let destinations: Vec<Destination> = parse_har(har).unwrap().iter().map(|r| r.request.destination).collect();
+    assert_eq!(destinations, [Destination::Document, Destination::IFrame]);
+}
+
+#[test]
+fn har_replay_ignores_recorded_response_policies() {
+    let har = r#"{"log": {"entries": [
+        {"_resourceType": "document", "request": {"url": "https://www.example.com/"}, "response": {"status": 200}},
+        {"_resourceType": "document", "request": {"url": "https://www.example.com/frame.html"}, "response": {"status": 200,
+            "headers": [{"name": "Content-Security-Policy", "value": "frame-ancestors 'none'; sandbox"}]}}
+    ]}}"#;
+    let mut recorded = parse_har(har).unwrap();
+    assert_eq!(recorded[1].response.csp_list.to_string(), "frame-ancestors 'none' ;sandbox ");
+    // A recorded worker whose own policy sandboxes it would be blocked by the response check.
+    let mut worker = recorded[1].clone();
+    worker.request.destination = Destination::Worker;
+    recorded.push(worker);
+    let csp_list = CspList::parse("frame-src 'self'; worker-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(replay(&recorded, &csp_list).is_empty());
+}
+
+#[test]
+fn har_replay_leaves_out_the_page_navigation() {
+    let har = r#"{"log": {"entries": [
+        {"_resourceType": "document", "request": {"url": "http://example.com/"}, "response": {"status": 301, "redirectURL": "https://www.example.com/"}},
+        {"_resourceType": "document", "request": {"url": "https://www.example.com/"}, "response": {"status": 200}},
+        {"_resourceType": "script", "request": {"url": "https://www.example.com/app.js"}, "response": {"status": 200}},
+        {"_resourceType": "script", "request": {"url": "https://cdn.example.net/lib.js"}, "response": {"status": 200}}
+    ]}}"#;
+    let recorded = parse_har(har).unwrap();
+    assert_eq!(recorded[1].request.destination, Destination::Document);
+    assert_eq!(recorded[1].request.redirect_count, 1);
+    // The candidate does not allow the page's own origin, but it is delivered by that page.
+    let csp_list = CspList::parse("default-src https://cdn.example.net", PolicySource::Header, PolicyDisposition::Enforce);
+    let blocked: Vec<String> = replay(&recorded, &csp_list).into_iter().map(|b| String::from(b.request.url)).collect();
+    assert_eq!(blocked, ["https://www.example.com/app.js"]);
+}
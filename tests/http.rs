@@ -0,0 +1,65 @@
+#![cfg(feature = "http")]
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::headers::HeaderMapExt;
+use content_security_policy::http::header::{HeaderMap, HeaderValue, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY};
+use content_security_policy::http_headers::*;
+
+fn header_map() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.append(CONTENT_SECURITY_POLICY, HeaderValue::from_static("script-src 'self', img-src 'none'"));
+    headers.append(CONTENT_SECURITY_POLICY_REPORT_ONLY, HeaderValue::from_static("style-src 'none'"));
+    headers.append(CONTENT_SECURITY_POLICY, HeaderValue::from_static("font-src 'none'"));
+    headers
+}
+
+#[test]
+fn http_from_header_map() {
+    let csp_list = CspList::from_header_map(&header_map());
+    assert_eq!(csp_list.to_string(), "script-src 'self' ,img-src 'none' ,font-src 'none' ,style-src 'none' ");
+    let dispositions: Vec<PolicyDisposition> = csp_list.0.iter().map(|p| p.disposition).collect();
+    assert_eq!(dispositions, vec![
+        PolicyDisposition::Enforce,
+        PolicyDisposition::Enforce,
+        PolicyDisposition::Enforce,
+        PolicyDisposition::Report,
+    ]);
+    assert!(csp_list.0.iter().all(|p| p.source == PolicySource::Header));
+}
+
+#[test]
+fn http_non_utf8_header() {
+    let mut headers = HeaderMap::new();
+    headers.append(CONTENT_SECURITY_POLICY, HeaderValue::from_bytes(b"img-src caf\xe9.example").unwrap());
+    let csp_list = CspList::from_header_map(&headers);
+    assert_eq!(csp_list.to_string(), "img-src caf\u{e9}.example ");
+    let values = csp_list.to_header_values().unwrap();
+    assert_eq!(values[0].1.as_bytes(), b"img-src caf\xe9.example ");
+}
+
+#[test]
+fn http_to_header_values() {
+    let csp_list = CspList::from_header_map(&header_map());
+    let mut headers = HeaderMap::new();
+    csp_list.append_to_header_map(&mut headers).unwrap();
+    assert_eq!(headers.get_all(CONTENT_SECURITY_POLICY).iter().count(), 3);
+    assert_eq!(headers.get_all(CONTENT_SECURITY_POLICY_REPORT_ONLY).iter().count(), 1);
+    assert_eq!(CspList::from_header_map(&headers).to_string(), csp_list.to_string());
+    let utf8 = CspList::parse("img-src \u{1F600}", PolicySource::Header, PolicyDisposition::Enforce);
+    assert_eq!(utf8.to_header_values().unwrap()[0].1.as_bytes(), "img-src \u{1F600} ".as_bytes());
+    let unencodable = CspList::parse("img-src \u{1}", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(unencodable.to_header_values().is_err());
+}
+
+#[test]
+fn http_typed_header() {
+    let headers = header_map();
+    let enforced = headers.typed_get::<ContentSecurityPolicy>().unwrap();
+    assert_eq!(enforced.0.to_string(), "script-src 'self' ,img-src 'none' ,font-src 'none' ");
+    let report_only = headers.typed_get::<ContentSecurityPolicyReportOnly>().unwrap();
+    assert_eq!(report_only.0.0[0].disposition, PolicyDisposition::Report);
+    let mut encoded = HeaderMap::new();
+    encoded.typed_insert(enforced);
+    assert_eq!(encoded.get_all(CONTENT_SECURITY_POLICY).iter().count(), 3);
+    assert!(HeaderMap::new().typed_get::<ContentSecurityPolicy>().is_none());
+}
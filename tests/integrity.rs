@@ -0,0 +1,70 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+
+const BODY: &[u8] = b"alert(1);";
+const SHA256: &str = "sha256-5jFwrAK0UV47oFbVg/iCCBbxD8X1w+QvoOUepu4C2YA=";
+const SHA384: &str = "sha384-dnux3uAPxaf+IhCrFG1D/XVNzP1XLDNcn3Pe3jyxouEAoot5kfwC5u8rMwNhE5oi";
+const SHA512: &str = "sha512-yth/AKDfYyamGdVY92SJHjP5YqBda8LtutursuX70OzxIztHmFivMqd2l3Hm/STWljOjS5/MGmJn2+NkmIDPnw==";
+const WRONG_SHA384: &str = "sha384-AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA";
+
+#[test]
+fn integrity_matches_each_algorithm() {
+    let cases = [(SHA256, HashAlgorithm::Sha256), (SHA384, HashAlgorithm::Sha384), (SHA512, HashAlgorithm::Sha512)];
+    for &(metadata, algorithm) in &cases {
+        match does_response_match_metadata_list(BODY, metadata) {
+            IntegrityCheckResult::Matches(ref hash) => assert_eq!(hash.algorithm(), algorithm),
+            ref result => panic!("unexpected result {:?} for {}", result, metadata),
+        }
+    }
+}
+
+#[test]
+fn integrity_does_not_match_other_body() {
+    let result = does_response_match_metadata_list(b"alert(2);", SHA256);
+    assert!(!result.is_allowed());
+    match result {
+        IntegrityCheckResult::DoesNotMatch { ref expected, ref actual } => {
+            assert_eq!(expected.len(), 1);
+            assert_eq!(actual.algorithm(), HashAlgorithm::Sha256);
+            assert_eq!(actual.value(), HashAlgorithm::Sha256.apply_to_bytes(b"alert(2);"));
+        }
+        ref result => panic!("unexpected result {:?}", result),
+    }
+}
+
+#[test]
+fn integrity_uses_strongest_algorithm() {
+    // The sha256 hash is right, but only the wrong sha384 hash is checked.
+    let metadata = format!("{} {}", SHA256, WRONG_SHA384);
+    assert!(!does_response_match_metadata_list(BODY, &metadata).is_allowed());
+    // Any of the hashes with the strongest algorithm may match.
+    let metadata = format!("{} {} {}", WRONG_SHA384, SHA256, SHA384);
+    assert_eq!(
+        parse_subresource_integrity_metadata(&metadata).strongest_metadata().len(),
+        2
+    );
+    assert!(does_response_match_metadata_list(BODY, &metadata).is_allowed());
+}
+
+#[test]
+fn integrity_options_are_ignored() {
+    let metadata = format!("{}?foo=bar?baz", SHA384);
+    assert!(does_response_match_metadata_list(BODY, &metadata).is_allowed());
+    let metadata = format!("{}?ct=application/javascript", WRONG_SHA384);
+    assert!(!does_response_match_metadata_list(BODY, &metadata).is_allowed());
+}
+
+#[test]
+fn integrity_no_usable_metadata() {
+    assert_eq!(does_response_match_metadata_list(BODY, ""), IntegrityCheckResult::NoMetadata);
+    assert_eq!(does_response_match_metadata_list(BODY, "md5-abc sha1-def"), IntegrityCheckResult::NoMetadata);
+    assert!(does_response_match_metadata_list(BODY, "  \t").is_allowed());
+    // Tokens must be a whole hash expression.
+    assert_eq!(does_response_match_metadata_list(BODY, &format!("x{}", WRONG_SHA384)), IntegrityCheckResult::NoMetadata);
+}
+
+#[test]
+fn integrity_accepts_base64url() {
+    let metadata = SHA256.replace('/', "_").replace('+', "-");
+    assert!(does_response_match_metadata_list(BODY, &metadata).is_allowed());
+}
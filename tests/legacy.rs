@@ -0,0 +1,140 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+#[macro_use]
+mod common;
+use common::{csp_list as legacy, request};
+
+fn enforce() -> RequestCheckOptions {
+    RequestCheckOptions::default().legacy_directives(LegacyDirectives::Enforce)
+}
+
+test_should_request_be_blocked!{
+    (   name: block_all_mixed_content_allows_secure_requests,
+        url: "https://www.notriddle.com/image.png",
+        origin: "https://www.notriddle.com",
+        policy: "block-all-mixed-content",
+        dest: Image,
+        options: enforce(),
+        result: Allowed),
+    (   name: block_all_mixed_content_allows_data_urls,
+        url: "data:image/png,",
+        origin: "https://www.notriddle.com",
+        policy: "block-all-mixed-content",
+        dest: Image,
+        options: enforce(),
+        result: Allowed),
+    (   name: prefetch_src_allows_listed_prefetches,
+        url: "https://cdn.notriddle.com/next.html",
+        origin: "https://www.notriddle.com",
+        policy: "default-src 'none'; prefetch-src https://cdn.notriddle.com",
+        dest: None,
+        initiator: Prefetch,
+        options: enforce(),
+        result: Allowed),
+    (   name: prefetch_src_leaves_fetches_to_default_src,
+        url: "https://cdn.notriddle.com/data.json",
+        origin: "https://www.notriddle.com",
+        policy: "default-src 'none'; prefetch-src https://cdn.notriddle.com",
+        dest: None,
+        initiator: Fetch,
+        options: enforce(),
+        result: Blocked),
+    (   name: navigate_to_is_not_a_fetch_directive,
+        url: "https://evil.example/",
+        origin: "https://www.notriddle.com",
+        policy: "navigate-to 'self'",
+        dest: Document,
+        result: Allowed),
+}
+
+#[test]
+fn legacy_directives_are_reported_as_diagnostics() {
+    let (csp_list, diagnostics) = CspList::parse_with_diagnostics(
+        "block-all-mixed-content; img-src *, plugin-types application/pdf; referrer never",
+        PolicySource::Header,
+        PolicyDisposition::Enforce,
+    );
+    assert_eq!(csp_list.0.len(), 2);
+    assert_eq!(diagnostics, vec![
+        ParseDiagnostic::DeprecatedDirective("block-all-mixed-content".to_owned()),
+        ParseDiagnostic::DeprecatedDirective("plugin-types".to_owned()),
+        ParseDiagnostic::DeprecatedDirective("referrer".to_owned()),
+    ]);
+    let (_, diagnostics) = CspList::parse_with_diagnostics("img-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn legacy_directives_are_ignored_by_default() {
+    let csp_list = legacy("block-all-mixed-content; default-src *; prefetch-src 'none'");
+    let insecure = request("http://www.notriddle.com/image.png", Destination::Image);
+    let prefetch = Request { initiator: Initiator::Prefetch, ..request("https://www.notriddle.com/next.html", Destination::None) };
+    assert_eq!(csp_list.should_request_be_blocked(&insecure).0, CheckResult::Allowed);
+    assert_eq!(csp_list.should_request_be_blocked(&prefetch).0, CheckResult::Allowed);
+    assert_eq!(RequestCheckOptions::default().legacy_directives, LegacyDirectives::Ignore);
+    // Enforcement is a check option, so it survives serializing the policy.
+    let csp_list = legacy(&csp_list.to_string());
+    assert_eq!(csp_list.should_request_be_blocked_with_options(&insecure, &enforce()).0, CheckResult::Blocked);
+    assert_eq!(csp_list.should_request_be_blocked_with_options(&prefetch, &enforce()).0, CheckResult::Blocked);
+}
+
+#[test]
+fn block_all_mixed_content_blocks_insecure_requests() {
+    let (check_result, violations) = legacy("block-all-mixed-content").should_request_be_blocked_with_options(
+        &request("http://www.notriddle.com/image.png", Destination::Image), &enforce());
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations[0].effective_directive, "block-all-mixed-content");
+}
+
+#[test]
+fn prefetch_src_replaces_default_src_for_prefetches() {
+    let request = Request { initiator: Initiator::Prerender, ..request("https://evil.example/next.html", Destination::None) };
+    let (check_result, violations) = legacy("default-src 'none'; prefetch-src https://cdn.notriddle.com")
+        .should_request_be_blocked_with_options(&request, &enforce());
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations[0].effective_directive, "prefetch-src");
+}
+
+#[test]
+fn plugin_types_matches_media_types() {
+    let csp_list = legacy("plugin-types application/pdf");
+    let url = Url::parse("https://www.notriddle.com/document.pdf").unwrap();
+    assert_eq!(csp_list.should_plugin_be_blocked(&url, "Application/PDF").0, CheckResult::Allowed);
+    let (check_result, violations) = csp_list.should_plugin_be_blocked(&url, "application/x-shockwave-flash");
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations[0].effective_directive, "plugin-types");
+    match violations[0].resource {
+        ViolationResource::Url(ref violation_url) => assert_eq!(violation_url, &url),
+        ref resource => panic!("unexpected resource {:?}", resource),
+    }
+}
+
+#[test]
+fn navigate_to_checks_navigations() {
+    let csp_list = legacy("navigate-to 'self' https://docs.notriddle.com");
+    let (check_result, _) = csp_list.should_navigation_request_be_blocked(
+        &request("https://docs.notriddle.com/", Destination::Document));
+    assert_eq!(check_result, CheckResult::Allowed);
+    let (check_result, violations) = csp_list.should_navigation_request_be_blocked(
+        &request("https://evil.example/", Destination::Document));
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations[0].effective_directive, "navigate-to");
+}
+
+#[test]
+fn referrer_maps_to_referrer_policy() {
+    assert_eq!(legacy("referrer never").legacy_referrer_policy(), Some("no-referrer".to_owned()));
+    assert_eq!(legacy("referrer always").legacy_referrer_policy(), Some("unsafe-url".to_owned()));
+    assert_eq!(legacy("referrer default").legacy_referrer_policy(), Some("no-referrer-when-downgrade".to_owned()));
+    assert_eq!(legacy("referrer origin-when-crossorigin").legacy_referrer_policy(), Some("origin-when-cross-origin".to_owned()));
+    assert_eq!(legacy("referrer origin, referrer never").legacy_referrer_policy(), Some("no-referrer".to_owned()));
+    assert_eq!(legacy("img-src *").legacy_referrer_policy(), None);
+}
+
+#[test]
+fn reflected_xss_maps_to_x_xss_protection() {
+    assert_eq!(legacy("reflected-xss allow").legacy_x_xss_protection(), Some("0"));
+    assert_eq!(legacy("reflected-xss filter").legacy_x_xss_protection(), Some("1"));
+    assert_eq!(legacy("reflected-xss BLOCK").legacy_x_xss_protection(), Some("1; mode=block"));
+    assert_eq!(legacy("reflected-xss sometimes").legacy_x_xss_protection(), None);
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "tower")]
+extern crate content_security_policy;
+extern crate futures_executor;
+extern crate hyper;
+extern crate hyper_util;
+extern crate tokio;
+extern crate tower_layer;
+extern crate tower_service;
+use content_security_policy::*;
+use content_security_policy::http::header::{HeaderValue, CONTENT_SECURITY_POLICY, CONTENT_SECURITY_POLICY_REPORT_ONLY};
+use content_security_policy::http::{Request, Response, StatusCode};
+use content_security_policy::middleware::*;
+use futures_executor::block_on;
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::task::{Context, Poll};
+use tower_layer::Layer;
+use tower_service::Service;
+
+/// Responds with the request's nonce as the body, and with `upstream_csp` as its CSP header.
+struct Handler {
+    upstream_csp: Option<&'static str>,
+}
+
+impl Service<Request<()>> for Handler {
+    type Response = Response<String>;
+    type Error = Infallible;
+    type Future = Ready<Result<Response<String>, Infallible>>;
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: Request<()>) -> Self::Future {
+        let nonce = request.extensions().get::<CspNonce>().unwrap().0.clone();
+        let mut response = Response::new(nonce);
+        if let Some(upstream_csp) = self.upstream_csp {
+            response.headers_mut().insert(CONTENT_SECURITY_POLICY, HeaderValue::from_static(upstream_csp));
+        }
+        ready(Ok(response))
+    }
+}
+
+fn call(layer: &CspLayer, upstream_csp: Option<&'static str>) -> Response<String> {
+    let mut service = layer.layer(Handler { upstream_csp });
+    block_on(service.call(Request::new(()))).unwrap()
+}
+
+#[test]
+fn middleware_adds_nonce() {
+    let layer = CspLayer::new(Policy::parse("script-src 'self'; object-src 'none'", PolicySource::Header, PolicyDisposition::Enforce));
+    let response = call(&layer, None);
+    let nonce = response.body().clone();
+    assert!(!nonce.is_empty());
+    let header = response.headers().get(CONTENT_SECURITY_POLICY).unwrap().to_str().unwrap();
+    assert_eq!(header, format!("script-src 'self' 'nonce-{}' ;object-src 'none' ", nonce));
+    let second = call(&layer, None);
+    assert_ne!(second.body(), &nonce);
+}
+
+#[test]
+fn middleware_report_only_and_from_fn() {
+    let layer = CspLayer::from_fn(|nonce| {
+        let mut csp_list = CspList::parse("img-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+        csp_list.append(CspList::parse(&format!("script-src 'nonce-{}' 'strict-dynamic'", nonce), PolicySource::Header, PolicyDisposition::Report));
+        csp_list
+    });
+    let response = call(&layer, Some("frame-ancestors 'none'"));
+    let nonce = response.body().clone();
+    let enforced: Vec<&str> = response.headers().get_all(CONTENT_SECURITY_POLICY).iter().map(|v| v.to_str().unwrap()).collect();
+    assert_eq!(enforced, vec!["frame-ancestors 'none'", "img-src 'self' "]);
+    let report_only = response.headers().get(CONTENT_SECURITY_POLICY_REPORT_ONLY).unwrap().to_str().unwrap();
+    assert_eq!(report_only, format!("script-src 'nonce-{}' 'strict-dynamic' ", nonce));
+}
+
+#[test]
+fn middleware_require_upstream() {
+    let baseline = CspList::parse("object-src 'none'; base-uri 'none'", PolicySource::Header, PolicyDisposition::Enforce);
+    let layer = CspLayer::from_fn(|_| CspList(Vec::new())).require_upstream(baseline);
+    let response = call(&layer, Some("base-uri 'none'; object-src 'none'"));
+    assert_eq!(response.status(), StatusCode::OK);
+    let response = call(&layer, Some("object-src 'none'"));
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert!(response.body().is_empty());
+    let response = call(&layer, None);
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+}
+
+#[test]
+fn middleware_require_upstream_allows_stricter_policies() {
+    let baseline = CspList::parse("script-src 'self' https://cdn.notriddle.com; sandbox allow-scripts allow-forms; report-uri /csp", PolicySource::Header, PolicyDisposition::Enforce);
+    let layer = CspLayer::from_fn(|_| CspList(Vec::new())).require_upstream(baseline);
+    let status = |upstream_csp| call(&layer, Some(upstream_csp)).status();
+    assert_eq!(status("script-src 'none'; sandbox"), StatusCode::OK);
+    assert_eq!(status("script-src 'SELF'; sandbox allow-scripts"), StatusCode::OK);
+    assert_eq!(status("sandbox allow-forms, script-src https://cdn.notriddle.com"), StatusCode::OK);
+    assert_eq!(status("script-src 'self' https:; sandbox"), StatusCode::BAD_GATEWAY);
+    assert_eq!(status("script-src 'self'; sandbox allow-scripts allow-popups"), StatusCode::BAD_GATEWAY);
+    assert_eq!(status("default-src 'none'; sandbox"), StatusCode::BAD_GATEWAY);
+}
+
+#[test]
+fn middleware_leaves_unsafe_inline_alone() {
+    let layer = CspLayer::new(Policy::parse(
+        "script-src 'self' 'unsafe-inline'; script-src-elem 'unsafe-inline' 'strict-dynamic' https:",
+        PolicySource::Header,
+        PolicyDisposition::Enforce,
+    ));
+    let response = call(&layer, None);
+    let nonce = response.body().clone();
+    let header = response.headers().get(CONTENT_SECURITY_POLICY).unwrap().to_str().unwrap();
+    assert_eq!(header, format!("script-src 'self' 'unsafe-inline' ;script-src-elem 'unsafe-inline' 'strict-dynamic' https: 'nonce-{}' ", nonce));
+}
+
+/// Responds to any request with a fixed page, like a real upstream would.
+#[derive(Clone)]
+struct Page;
+
+impl<B> Service<Request<B>> for Page {
+    type Response = Response<String>;
+    type Error = Infallible;
+    type Future = Ready<Result<Response<String>, Infallible>>;
+    fn poll_ready(&mut self, _cx: &mut Context) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let nonce = &request.extensions().get::<CspNonce>().unwrap().0;
+        ready(Ok(Response::new(format!("<script nonce=\"{}\"></script>", nonce))))
+    }
+}
+
+#[test]
+fn middleware_over_http() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        stream.set_nonblocking(true).unwrap();
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_io().build().unwrap();
+        let _guard = runtime.enter();
+        let stream = hyper_util::rt::TokioIo::new(tokio::net::TcpStream::from_std(stream).unwrap());
+        let layer = CspLayer::new(Policy::parse("script-src 'self'", PolicySource::Header, PolicyDisposition::Enforce));
+        let service = hyper_util::service::TowerToHyperService::new(layer.layer(Page));
+        runtime.block_on(hyper::server::conn::http1::Builder::new().serve_connection(stream, service)).unwrap();
+    });
+    let mut client = TcpStream::connect(address).unwrap();
+    client.write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n").unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).unwrap();
+    server.join().unwrap();
+    let (head, body) = response.split_at(response.find("\r\n\r\n").unwrap());
+    assert!(head.starts_with("HTTP/1.1 200 OK\r\n"));
+    let nonce = &body["\r\n\r\n<script nonce=\"".len()..body.len() - "\"></script>".len()];
+    let expected = format!("content-security-policy: script-src 'self' 'nonce-{}' \r\n", nonce);
+    assert!(head.to_ascii_lowercase().contains(&expected.to_ascii_lowercase()), "{}", head);
+}
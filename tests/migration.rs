@@ -0,0 +1,100 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::browser_profile::BrowserProfile;
+use content_security_policy::compatibility::{self, CspLevel};
+use content_security_policy::migration::{self, RequiredChange};
+#[macro_use]
+mod common;
+use common::request;
+
+fn parse(policy: &str) -> Policy {
+    Policy::parse(policy, PolicySource::Header, PolicyDisposition::Enforce)
+}
+
+#[test]
+fn allowlist_is_kept_as_fallback() {
+    let migration = migration::migrate_to_strict(&parse("default-src 'self'; script-src 'self' https://cdn.notriddle.com 'unsafe-inline'"));
+    assert_eq!(
+        migration.policy.to_string(),
+        "default-src 'self' ;script-src 'strict-dynamic' 'self' https://cdn.notriddle.com 'unsafe-inline' ;object-src 'none' ;base-uri 'none' ",
+    );
+    assert_eq!(migration.required_changes, vec![
+        RequiredChange::NonceInlineScripts,
+        RequiredChange::MoveInlineEventHandlers,
+        RequiredChange::NonceExternalScripts(vec!["'self'".to_owned(), "https://cdn.notriddle.com".to_owned()]),
+        RequiredChange::RemovePlugins,
+        RequiredChange::RemoveBaseElements,
+    ]);
+}
+
+#[test]
+fn candidate_is_equivalent_in_csp2_and_strict_in_csp3() {
+    let migration = migration::migrate_to_strict(&parse("script-src https://cdn.notriddle.com; object-src 'none'"));
+    let policy = migration.policy.to_string().replace("'strict-dynamic'", "'strict-dynamic' 'nonce-abc'");
+    let csp_list = CspList::parse(&policy, PolicySource::Header, PolicyDisposition::Enforce);
+    let csp2 = RequestCheckOptions::default().browser_profile(BrowserProfile::CSP2);
+    let allowlisted = Request { parser_metadata: ParserMetadata::ParserInserted, ..request("https://cdn.notriddle.com/a.js", Destination::Script) };
+    assert_eq!(csp_list.should_request_be_blocked_with_options(&allowlisted, &csp2).0, CheckResult::Allowed);
+    assert_eq!(csp_list.should_request_be_blocked(&allowlisted).0, CheckResult::Blocked);
+    let nonced = Request { nonce: "abc".to_owned(), ..allowlisted.clone() };
+    assert_eq!(csp_list.should_request_be_blocked(&nonced).0, CheckResult::Allowed);
+}
+
+#[test]
+fn none_is_left_alone() {
+    let migration = migration::migrate_to_strict(&parse("script-src 'none'; object-src 'none'; base-uri 'none'"));
+    assert_eq!(migration.policy.to_string(), "script-src 'none' ;object-src 'none' ;base-uri 'none' ");
+    assert!(migration.required_changes.is_empty());
+}
+
+#[test]
+fn unrestricted_scripts_get_an_open_fallback() {
+    let migration = migration::migrate_to_strict(&parse("img-src *"));
+    assert_eq!(
+        migration.policy.to_string(),
+        "img-src * ;script-src 'strict-dynamic' 'unsafe-inline' https: http: ;object-src 'none' ;base-uri 'none' ",
+    );
+    assert!(migration.required_changes.contains(&RequiredChange::RemoveEval));
+    assert!(migration.required_changes.contains(&RequiredChange::RemovePlugins));
+    assert!(migration.required_changes.contains(&RequiredChange::NonceInlineScripts));
+}
+
+#[test]
+fn hashes_are_kept() {
+    let migration = migration::migrate_to_strict(&parse("script-src 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='; object-src 'none'"));
+    assert_eq!(
+        migration.policy.to_string(),
+        "script-src 'strict-dynamic' 'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI=' ;object-src 'none' ;base-uri 'none' ",
+    );
+    assert_eq!(migration.required_changes, vec![RequiredChange::RemoveBaseElements]);
+    let report = compatibility::analyze(&migration.policy);
+    assert!(report.level(CspLevel::Csp3).is_protected());
+}
+
+#[test]
+fn script_src_elem_is_migrated_too() {
+    let migration = migration::migrate_to_strict(&parse("script-src 'self'; script-src-elem https://cdn.notriddle.com"));
+    assert_eq!(
+        migration.policy.to_string(),
+        "script-src 'strict-dynamic' 'self' ;script-src-elem 'strict-dynamic' https://cdn.notriddle.com ;object-src 'none' ;base-uri 'none' ",
+    );
+}
+
+#[test]
+fn element_and_attribute_directives_are_migrated_on_their_own() {
+    let migration = migration::migrate_to_strict(&parse(
+        "script-src 'none'; script-src-elem https://cdn.notriddle.com 'unsafe-inline'; script-src-attr 'unsafe-inline'; object-src 'none'"));
+    assert_eq!(
+        migration.policy.to_string(),
+        "script-src 'none' ;script-src-elem 'strict-dynamic' https://cdn.notriddle.com 'unsafe-inline' ;script-src-attr 'none' ;object-src 'none' ;base-uri 'none' ",
+    );
+    assert_eq!(migration.required_changes, vec![
+        RequiredChange::NonceInlineScripts,
+        RequiredChange::MoveInlineEventHandlers,
+        RequiredChange::NonceExternalScripts(vec!["https://cdn.notriddle.com".to_owned()]),
+        RequiredChange::RemoveBaseElements,
+    ]);
+    // Inline event handlers are governed by `script-src-attr`, which already blocks them.
+    let migration = migration::migrate_to_strict(&parse("script-src 'unsafe-inline'; script-src-attr 'none'; object-src 'none'; base-uri 'none'"));
+    assert_eq!(migration.required_changes, vec![RequiredChange::NonceInlineScripts]);
+}
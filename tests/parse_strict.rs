@@ -0,0 +1,95 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+use std::convert::TryFrom;
+use std::error::Error;
+
+fn error(serialized: &str) -> PolicyError {
+    Policy::parse_strict(serialized, PolicySource::Header, PolicyDisposition::Enforce).unwrap_err()
+}
+
+#[test]
+fn valid_policy_parses_like_parse() {
+    let serialized = "default-src 'self'; Script-Src 'nonce-abc' https://cdn.notriddle.com;";
+    let strict = Policy::parse_strict(serialized, PolicySource::Meta, PolicyDisposition::Report).unwrap();
+    let lenient = Policy::parse(serialized, PolicySource::Meta, PolicyDisposition::Report);
+    assert_eq!(strict.to_string(), lenient.to_string());
+    assert_eq!(strict.source, PolicySource::Meta);
+    assert_eq!(strict.disposition, PolicyDisposition::Report);
+}
+
+#[test]
+fn empty_policy_is_an_error() {
+    assert_eq!(error(" ; ;"), PolicyError { kind: PolicyErrorKind::Empty, position: 0 });
+}
+
+#[test]
+fn duplicate_directive_is_an_error() {
+    assert_eq!(error("script-src 'self'; img-src *; SCRIPT-SRC *"), PolicyError {
+        kind: PolicyErrorKind::DuplicateDirective("script-src".to_owned()),
+        position: 30,
+    });
+}
+
+#[test]
+fn invalid_directive_name_is_an_error() {
+    assert_eq!(error("img-src *; script_src 'self'"), PolicyError {
+        kind: PolicyErrorKind::InvalidDirectiveName("script_src".to_owned()),
+        position: 11,
+    });
+}
+
+#[test]
+fn invalid_directive_value_is_an_error() {
+    let error = error("img-src https://caf\u{e9}.example");
+    assert_eq!(error, PolicyError {
+        kind: PolicyErrorKind::InvalidDirectiveValue {
+            directive: "img-src".to_owned(),
+            token: "https://caf\u{e9}.example".to_owned(),
+        },
+        position: 8,
+    });
+    assert_eq!(error.to_string(), "invalid value \"https://caf\u{e9}.example\" for directive img-src at byte 8");
+}
+
+#[test]
+fn from_str_and_try_from() {
+    let policy: Policy = "script-src 'self'".parse().unwrap();
+    assert_eq!(policy.source, PolicySource::Header);
+    assert_eq!(policy.disposition, PolicyDisposition::Enforce);
+    assert!(Policy::try_from("script-src 'self'").is_ok());
+    assert!("".parse::<Policy>().is_err());
+    let csp_list: CspList = "script-src 'self', img-src *".parse().unwrap();
+    assert_eq!(csp_list.0.len(), 2);
+    assert!(CspList::try_from("img-src *").is_ok());
+}
+
+#[test]
+fn list_positions_are_offsets_in_the_list() {
+    let error = CspList::parse_strict("img-src *, script-src 'self' 'self'; script-src *", PolicySource::Header, PolicyDisposition::Enforce).unwrap_err();
+    assert_eq!(error, PolicyError {
+        kind: PolicyErrorKind::DuplicateDirective("script-src".to_owned()),
+        position: 37,
+    });
+    let error = "img-src *, , script-src 'self'".parse::<CspList>().unwrap_err();
+    assert_eq!(error, PolicyError { kind: PolicyErrorKind::Empty, position: 10 });
+}
+
+#[test]
+fn empty_list_members_are_errors() {
+    assert_eq!(",img-src *".parse::<CspList>().unwrap_err(), PolicyError { kind: PolicyErrorKind::Empty, position: 0 });
+    assert_eq!("img-src *,,script-src 'self'".parse::<CspList>().unwrap_err(), PolicyError { kind: PolicyErrorKind::Empty, position: 10 });
+    assert_eq!("".parse::<CspList>().unwrap_err(), PolicyError { kind: PolicyErrorKind::Empty, position: 0 });
+    assert_eq!("img-src *,".parse::<CspList>().unwrap_err(), PolicyError { kind: PolicyErrorKind::Empty, position: 10 });
+}
+
+fn load_config(serialized: &str) -> Result<CspList, Box<dyn Error>> {
+    let csp_list = serialized.parse::<CspList>()?;
+    Ok(csp_list)
+}
+
+#[test]
+fn errors_work_with_question_mark() {
+    assert!(load_config("img-src *").is_ok());
+    let error = load_config("img-src *; img-src 'self'").unwrap_err();
+    assert_eq!(error.to_string(), "duplicate directive img-src at byte 11");
+}
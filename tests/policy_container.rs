@@ -0,0 +1,87 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::policy_container::*;
+
+fn container(policy: &str) -> PolicyContainer {
+    PolicyContainer::new(CspList::parse(policy, PolicySource::Header, PolicyDisposition::Enforce))
+}
+
+fn response(url: &str, policy: &str) -> Response {
+    Response {
+        csp_list: CspList::parse(policy, PolicySource::Header, PolicyDisposition::Enforce),
+        url: Url::parse(url).unwrap(),
+        redirect_count: 0,
+    }
+}
+
+#[test]
+fn policy_container_local_urls() {
+    assert!(url_is_local(&Url::parse("about:blank").unwrap()));
+    assert!(url_is_local(&Url::parse("data:text/html,hi").unwrap()));
+    assert!(url_is_local(&Url::parse("blob:https://example.com/1234").unwrap()));
+    assert!(!url_is_local(&Url::parse("https://example.com/").unwrap()));
+    assert!(requires_storing_the_policy_container_in_history(&Url::parse("about:blank").unwrap()));
+    assert!(!requires_storing_the_policy_container_in_history(&Url::parse("blob:https://example.com/1234").unwrap()));
+}
+
+#[test]
+fn policy_container_from_response() {
+    let from_response = PolicyContainer::create_from_fetch_response(&response("https://example.com/", "img-src 'none'"), None);
+    assert_eq!(from_response.csp_list.to_string(), "img-src 'none' ");
+    let blob_creator = container("script-src 'self'");
+    let from_blob = PolicyContainer::create_from_fetch_response(
+        &response("blob:https://example.com/1234", "img-src 'none'"),
+        Some(&blob_creator),
+    );
+    assert_eq!(from_blob.csp_list.to_string(), "script-src 'self' ");
+}
+
+#[test]
+fn policy_container_navigation_inherits_for_local_urls() {
+    let initiator = container("script-src 'none'");
+    let parent = container("style-src 'none'");
+    let from_response = container("img-src 'none'");
+    let about_blank = Url::parse("about:blank").unwrap();
+    let result = determine_navigation_params_policy_container(&about_blank, None, Some(&initiator), Some(&parent), None);
+    assert_eq!(result.csp_list.to_string(), "script-src 'none' ");
+    let srcdoc = Url::parse("about:srcdoc").unwrap();
+    let result = determine_navigation_params_policy_container(&srcdoc, None, Some(&initiator), Some(&parent), None);
+    assert_eq!(result.csp_list.to_string(), "style-src 'none' ");
+    let history = container("font-src 'none'");
+    let result = determine_navigation_params_policy_container(&about_blank, Some(&history), Some(&initiator), None, None);
+    assert_eq!(result.csp_list.to_string(), "font-src 'none' ");
+    let https = Url::parse("https://example.com/").unwrap();
+    let result = determine_navigation_params_policy_container(&https, None, Some(&initiator), None, Some(from_response));
+    assert_eq!(result.csp_list.to_string(), "img-src 'none' ");
+    let result = determine_navigation_params_policy_container(&https, None, Some(&initiator), None, None);
+    assert!(result.csp_list.0.is_empty());
+}
+
+#[test]
+fn policy_container_navigation_with_inconsistent_inputs() {
+    let initiator = container("script-src 'none'");
+    let history = container("font-src 'none'");
+    // A history policy container is only stored for local URLs, so it is ignored for others.
+    let https = Url::parse("https://example.com/").unwrap();
+    let result = determine_navigation_params_policy_container(
+        &https, Some(&history), Some(&initiator), None, Some(container("img-src 'none'")));
+    assert_eq!(result.csp_list.to_string(), "img-src 'none' ");
+    // `about:srcdoc` without a parent is handled like any other local URL.
+    let srcdoc = Url::parse("about:srcdoc").unwrap();
+    let result = determine_navigation_params_policy_container(&srcdoc, None, Some(&initiator), None, None);
+    assert_eq!(result.csp_list.to_string(), "script-src 'none' ");
+    let result = determine_navigation_params_policy_container(&srcdoc, None, None, None, None);
+    assert!(result.csp_list.0.is_empty());
+}
+
+#[test]
+fn policy_container_worker() {
+    let owner = container("connect-src 'none'");
+    let result = initialize_a_worker_global_scopes_policy_container(&response("data:text/javascript,1", ""), &owner, None);
+    assert_eq!(result.csp_list.to_string(), "connect-src 'none' ");
+    let result = initialize_a_worker_global_scopes_policy_container(&response("https://example.com/w.js", "img-src 'none'"), &owner, None);
+    assert_eq!(result.csp_list.to_string(), "img-src 'none' ");
+    let blob_creator = container("script-src 'self'");
+    let result = initialize_a_worker_global_scopes_policy_container(&response("blob:https://example.com/1234", ""), &owner, Some(&blob_creator));
+    assert_eq!(result.csp_list.to_string(), "script-src 'self' ");
+}
@@ -0,0 +1,132 @@
+//! Property tests for the parsers and the matching algorithms.
+//!
+//! Policies come from attacker-controlled headers, so none of these entry points may panic.
+
+extern crate content_security_policy;
+extern crate proptest;
+use content_security_policy::*;
+use content_security_policy::sandboxing_directive::parse_a_sandboxing_directive;
+use proptest::prelude::*;
+
+/// Source expressions that exercise each branch of the matching algorithms,
+/// including malformed ones.
+fn source_expression() -> impl Strategy<Value = String> {
+    prop_oneof![
+        Just("*".to_owned()),
+        Just("'self'".to_owned()),
+        Just("'none'".to_owned()),
+        Just("'unsafe-inline'".to_owned()),
+        Just("'unsafe-hashes'".to_owned()),
+        Just("'strict-dynamic'".to_owned()),
+        Just("'report-sample'".to_owned()),
+        Just("'nonce-abc'".to_owned()),
+        Just("'sha256-bhHHL3z2vDgxUt0W3dWQOrprscmda2Y5pLsLg4GF+pI='".to_owned()),
+        "[a-z]{1,5}:",
+        "([a-z]{1,5}://)?(\\*|\\*\\.)?[a-z0-9.\\-\\[\\]:]{0,12}(:(\\*|[0-9]{1,5}))?(/[a-z%./]{0,8})?",
+        "\\PC{0,10}",
+    ]
+}
+
+fn policy() -> impl Strategy<Value = String> {
+    let directive_name = prop_oneof![
+        Just("default-src"), Just("script-src"), Just("script-src-elem"), Just("script-src-attr"),
+        Just("style-src"), Just("style-src-elem"), Just("style-src-attr"), Just("img-src"),
+        Just("connect-src"), Just("worker-src"), Just("child-src"), Just("frame-src"),
+        Just("object-src"), Just("sandbox"), Just("fenced-frame-src"), Just("unknown"),
+    ];
+    let directive = (directive_name, prop::collection::vec(source_expression(), 0..4))
+        .prop_map(|(name, value)| format!("{} {}", name, value.join(" ")));
+    prop::collection::vec(directive, 0..4).prop_map(|d| d.join("; "))
+}
+
+fn url() -> impl Strategy<Value = Url> {
+    prop_oneof![
+        "(http|https|ws|wss|ftp|data|blob|about|app)://[a-z0-9.\\-]{1,12}(:[0-9]{1,5})?(/[a-z%./]{0,8})?",
+        Just("http://[::1]/".to_owned()),
+        Just("http://127.0.0.1/".to_owned()),
+        Just("http://xn--mnchen-3ya.example/".to_owned()),
+        Just("data:text/javascript,alert(1)".to_owned()),
+        Just("about:blank".to_owned()),
+    ].prop_filter_map("unparsable URL", |url| Url::parse(&url).ok())
+}
+
+fn destination() -> impl Strategy<Value = Destination> {
+    use Destination::*;
+    prop_oneof![
+        Just(None), Just(Audio), Just(AudioWorklet), Just(Document), Just(Embed), Just(FencedFrame),
+        Just(Font), Just(Frame), Just(IFrame), Just(Image), Just(Json), Just(Manifest), Just(Object),
+        Just(PaintWorklet), Just(Report), Just(Script), Just(ServiceWorker), Just(SharedWorker),
+        Just(SpeculationRules), Just(Style), Just(Track), Just(Video), Just(WebIdentity), Just(Worker),
+        Just(Xslt),
+    ]
+}
+
+fn initiator() -> impl Strategy<Value = Initiator> {
+    prop_oneof![
+        Just(Initiator::Prefetch), Just(Initiator::Prerender), Just(Initiator::Fetch), Just(Initiator::None),
+    ]
+}
+
+fn request() -> impl Strategy<Value = Request> {
+    (url(), url(), 0..3u32, destination(), initiator(), "(abc)?", "\\PC{0,20}")
+        .prop_map(|(url, origin, redirect_count, destination, initiator, nonce, integrity_metadata)| Request {
+            url,
+            origin: origin.origin(),
+            redirect_count,
+            destination,
+            initiator,
+            nonce,
+            integrity_metadata,
+            parser_metadata: ParserMetadata::ParserInserted,
+        })
+}
+
+proptest! {
+    #[test]
+    fn property_display_is_a_parse_fixpoint(s in "\\PC*") {
+        let parsed = CspList::parse(&s, PolicySource::Header, PolicyDisposition::Enforce).to_string();
+        let reparsed = CspList::parse(&parsed, PolicySource::Header, PolicyDisposition::Enforce).to_string();
+        prop_assert_eq!(parsed, reparsed);
+    }
+
+    #[test]
+    fn property_generated_policy_round_trips(s in policy()) {
+        let parsed = CspList::parse(&s, PolicySource::Header, PolicyDisposition::Enforce).to_string();
+        let reparsed = CspList::parse(&parsed, PolicySource::Header, PolicyDisposition::Enforce).to_string();
+        prop_assert_eq!(parsed, reparsed);
+    }
+
+    #[test]
+    fn property_parsers_do_not_panic(s in "\\PC*") {
+        let _ = parse_subresource_integrity_metadata(&s);
+        let tokens: Vec<String> = s.split(' ').map(String::from).collect();
+        let _ = parse_a_sandboxing_directive(&tokens);
+    }
+
+    #[test]
+    fn property_checks_do_not_panic(
+        policy in policy(),
+        request in request(),
+        response_url in url(),
+        source in "\\PC{0,50}",
+        nonce in prop::option::of("abc|\\PC{0,5}"),
+    ) {
+        let csp_list = CspList::parse(&policy, PolicySource::Header, PolicyDisposition::Enforce);
+        let _ = csp_list.is_valid();
+        let _ = csp_list.should_request_be_blocked(&request);
+        let _ = csp_list.report_violations_for_request(&request);
+        let response = Response { csp_list: csp_list.clone(), url: response_url, redirect_count: request.redirect_count };
+        let _ = csp_list.should_response_to_request_be_blocked(&request, &response);
+        let element = Element { nonce: nonce.map(Into::into) };
+        for &type_ in &[
+            InlineCheckType::Script,
+            InlineCheckType::ScriptAttribute,
+            InlineCheckType::Style,
+            InlineCheckType::StyleAttribute,
+            InlineCheckType::Navigation,
+            InlineCheckType::SpeculationRules,
+        ] {
+            let _ = csp_list.should_elements_inline_type_behavior_be_blocked(&element, type_, &source);
+        }
+    }
+}
@@ -0,0 +1,125 @@
+#![cfg(feature = "report-collector")]
+extern crate content_security_policy;
+extern crate futures_executor;
+extern crate tower_service;
+use content_security_policy::*;
+use content_security_policy::http::{Method, Request, StatusCode};
+use content_security_policy::http::header::CONTENT_TYPE;
+use content_security_policy::report_collector::*;
+use futures_executor::block_on;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tower_service::Service;
+
+const LEGACY_REPORT: &str = r#"{"csp-report": {
+    "document-uri": "https://example.com/page",
+    "referrer": "",
+    "violated-directive": "script-src-elem",
+    "effective-directive": "script-src-elem",
+    "original-policy": "script-src 'self'; report-uri /csp",
+    "disposition": "enforce",
+    "blocked-uri": "https://evil.example/x.js",
+    "line-number": 3,
+    "column-number": 7,
+    "source-file": "https://example.com/page",
+    "status-code": 200,
+    "script-sample": ""
+}}"#;
+
+const REPORTING_API_REPORTS: &str = r#"[
+    {"type": "csp-violation", "age": 10, "url": "https://example.com/page", "user_agent": "test", "body": {
+        "documentURL": "https://example.com/page",
+        "blockedURL": "inline",
+        "effectiveDirective": "style-src-attr",
+        "originalPolicy": "style-src 'self' 'report-sample'; report-to csp",
+        "sample": "color: red",
+        "disposition": "report",
+        "statusCode": 200
+    }},
+    {"type": "deprecation", "age": 10, "url": "https://example.com/page", "user_agent": "test", "body": {}}
+]"#;
+
+fn collector() -> (ReportCollector, Arc<Mutex<Vec<CspReport>>>) {
+    configured_collector(|builder| builder)
+}
+
+fn configured_collector<F>(configure: F) -> (ReportCollector, Arc<Mutex<Vec<CspReport>>>)
+    where F: FnOnce(ReportCollectorBuilder) -> ReportCollectorBuilder {
+    let reports = Arc::new(Mutex::new(Vec::new()));
+    let sink_reports = reports.clone();
+    let builder = ReportCollector::builder("/csp", move |report| sink_reports.lock().unwrap().push(report));
+    (configure(builder).build(), reports)
+}
+
+fn post(collector: &mut ReportCollector, path: &str, content_type: &str, body: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(path)
+        .header(CONTENT_TYPE, content_type)
+        .body(body.to_owned())
+        .unwrap();
+    block_on(collector.call(request)).unwrap().status()
+}
+
+#[test]
+fn report_collector_legacy_format() {
+    let (mut collector, reports) = collector();
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", LEGACY_REPORT), StatusCode::NO_CONTENT);
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].document_url, "https://example.com/page");
+    assert_eq!(reports[0].referrer, None);
+    assert_eq!(reports[0].blocked_url, Some("https://evil.example/x.js".to_owned()));
+    assert_eq!(reports[0].effective_directive, "script-src-elem");
+    assert_eq!(reports[0].disposition, PolicyDisposition::Enforce);
+    assert_eq!(reports[0].line_number, Some(3));
+    assert_eq!(reports[0].sample, None);
+}
+
+#[test]
+fn report_collector_reporting_api_format() {
+    let (mut collector, reports) = collector();
+    assert_eq!(post(&mut collector, "/csp", "application/reports+json", REPORTING_API_REPORTS), StatusCode::NO_CONTENT);
+    let reports = reports.lock().unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].effective_directive, "style-src-attr");
+    assert_eq!(reports[0].sample, Some("color: red".to_owned()));
+    assert_eq!(reports[0].disposition, PolicyDisposition::Report);
+}
+
+#[test]
+fn report_collector_rejects_bad_requests() {
+    let (mut collector, reports) = collector();
+    assert_eq!(post(&mut collector, "/other", "application/csp-report", LEGACY_REPORT), StatusCode::NOT_FOUND);
+    assert_eq!(post(&mut collector, "/csp", "text/plain", LEGACY_REPORT), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", "{"), StatusCode::BAD_REQUEST);
+    let get = Request::builder().uri("/csp").body(String::new()).unwrap();
+    assert_eq!(block_on(collector.call(get)).unwrap().status(), StatusCode::METHOD_NOT_ALLOWED);
+    let (mut collector, _) = configured_collector(|builder| builder.max_body_size(16));
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", LEGACY_REPORT), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(reports.lock().unwrap().is_empty());
+}
+
+#[test]
+fn report_collector_deduplicates_and_rate_limits() {
+    let (mut collector, reports) = configured_collector(|builder| builder.rate_limit(2, Duration::from_secs(3600)));
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", LEGACY_REPORT), StatusCode::NO_CONTENT);
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", LEGACY_REPORT), StatusCode::NO_CONTENT);
+    assert_eq!(reports.lock().unwrap().len(), 1);
+    assert_eq!(post(&mut collector, "/csp", "application/json", REPORTING_API_REPORTS), StatusCode::NO_CONTENT);
+    assert_eq!(reports.lock().unwrap().len(), 2);
+    let other = LEGACY_REPORT.replace("x.js", "y.js");
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", &other), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(reports.lock().unwrap().len(), 2);
+}
+
+#[test]
+fn report_collector_clones_share_limits() {
+    let (mut collector, reports) = configured_collector(|builder| builder.rate_limit(1, Duration::from_secs(3600)));
+    let mut clone = collector.clone();
+    assert_eq!(post(&mut collector, "/csp", "application/csp-report", LEGACY_REPORT), StatusCode::NO_CONTENT);
+    assert_eq!(post(&mut clone, "/csp", "application/csp-report", LEGACY_REPORT), StatusCode::NO_CONTENT);
+    let other = LEGACY_REPORT.replace("x.js", "y.js");
+    assert_eq!(post(&mut clone, "/csp", "application/csp-report", &other), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(reports.lock().unwrap().len(), 1);
+}
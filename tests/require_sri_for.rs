@@ -0,0 +1,93 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+#[macro_use]
+mod common;
+use common::{csp_list, request};
+
+fn sri() -> RequestCheckOptions {
+    RequestCheckOptions::default().require_sri_for(true)
+}
+
+const ASSET: &str = "https://cdn.notriddle.com/asset";
+const METADATA: &str = "sha384-dnux3uAPxaf+IhCrFG1D/XVNzP1XLDNcn3Pe3jyxouEAoot5kfwC5u8rMwNhE5oi";
+
+test_should_request_be_blocked!{
+    (   name: require_sri_for_is_opt_in,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for script style",
+        dest: Script,
+        result: Allowed),
+    (   name: require_sri_for_blocks_missing_style_metadata,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for script style",
+        dest: Style,
+        options: sri(),
+        result: Blocked),
+    (   name: require_sri_for_blocks_unparseable_metadata,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for script",
+        dest: Script,
+        integrity: "md5-abc sha1-def",
+        options: sri(),
+        result: Blocked),
+    (   name: require_sri_for_allows_metadata,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for script",
+        dest: Script,
+        integrity: METADATA,
+        options: sri(),
+        result: Allowed),
+    (   name: require_sri_for_only_listed_destinations,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for script",
+        dest: Style,
+        options: sri(),
+        result: Allowed),
+    (   name: require_sri_for_workers_are_scripts,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for SCRIPT style",
+        dest: Worker,
+        options: sri(),
+        result: Blocked),
+    (   name: require_sri_for_ignores_images,
+        url: ASSET,
+        origin: "https://www.notriddle.com",
+        policy: "require-sri-for script style",
+        dest: Image,
+        options: sri(),
+        result: Allowed),
+}
+
+#[test]
+fn require_sri_for_blocks_missing_metadata() {
+    let (check_result, violations) = csp_list("require-sri-for script style")
+        .should_request_be_blocked_with_options(&request(ASSET, Destination::Script), &sri());
+    assert_eq!(check_result, CheckResult::Blocked);
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].effective_directive, "require-sri-for");
+    assert_eq!(violations[0].directive.to_string(), "require-sri-for script style ");
+}
+
+#[test]
+fn require_sri_for_allows_metadata_without_violations() {
+    let request = Request { integrity_metadata: METADATA.to_owned(), ..request(ASSET, Destination::Script) };
+    let (_, violations) = csp_list("require-sri-for script").should_request_be_blocked_with_options(&request, &sri());
+    assert!(violations.is_empty());
+}
+
+#[test]
+fn require_sri_for_report_only() {
+    let csp_list = CspList::parse("require-sri-for style", PolicySource::Header, PolicyDisposition::Report);
+    let request = request(ASSET, Destination::Style);
+    let (check_result, _) = csp_list.should_request_be_blocked_with_options(&request, &sri());
+    assert_eq!(check_result, CheckResult::Allowed);
+    let violations = csp_list.report_violations_for_request_with_options(&request, &sri());
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].disposition, PolicyDisposition::Report);
+}
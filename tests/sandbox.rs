@@ -89,3 +89,108 @@ fn sandbox_test_allow_images() {
     );
     assert_eq!(check_result, CheckResult::Allowed);
 }
+
+#[test]
+fn sandbox_parse_new_keywords() {
+    use content_security_policy::sandboxing_directive::*;
+    let tokens: Vec<String> = vec!["allow-downloads".to_owned(), "ALLOW-STORAGE-ACCESS-BY-USER-ACTIVATION".to_owned()];
+    let flags = parse_a_sandboxing_directive(&tokens);
+    assert!(!flags.contains(SandboxingFlagSet::SANDBOXED_DOWNLOADS_BROWSING_CONTEXT_FLAG));
+    assert!(!flags.contains(SandboxingFlagSet::SANDBOXED_STORAGE_ACCESS_BY_USER_ACTIVATION_FLAG));
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG));
+    let tokens: Vec<String> = vec!["allow-top-navigation-by-user-activation".to_owned()];
+    let flags = parse_a_sandboxing_directive(&tokens);
+    assert!(!flags.contains(SandboxingFlagSet::SANDBOXED_CUSTOM_PROTOCOLS_NAVIGATION_BROWSING_CONTEXT_FLAG));
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_TOP_LEVEL_NAVIGATION_WITHOUT_USER_ACTIVATION_BROWSING_CONTEXT_FLAG));
+}
+
+#[test]
+fn sandbox_parse_reports_unknown_tokens() {
+    use content_security_policy::sandboxing_directive::*;
+    let tokens: Vec<String> = vec!["allow-scripts".to_owned(), "allow-everything".to_owned()];
+    let (flags, unknown) = parse_a_sandboxing_directive_with_diagnostics(&tokens);
+    assert!(!flags.contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+    assert_eq!(unknown, vec!["allow-everything"]);
+}
+
+#[test]
+fn sandbox_tokens_round_trip() {
+    use content_security_policy::sandboxing_directive::*;
+    let cases: &[&[&str]] = &[
+        &[],
+        &["allow-scripts", "allow-same-origin"],
+        &["allow-top-navigation"],
+        &["allow-top-navigation", "allow-top-navigation-by-user-activation"],
+        &["allow-top-navigation-by-user-activation", "allow-top-navigation-to-custom-protocols"],
+        &["allow-popups", "allow-downloads", "allow-forms", "allow-modals"],
+    ];
+    for case in cases {
+        let tokens: Vec<String> = case.iter().map(|t| t.to_string()).collect();
+        let flags = parse_a_sandboxing_directive(&tokens);
+        let serialized: Vec<String> = flags.to_tokens().iter().map(|t| t.to_string()).collect();
+        assert_eq!(parse_a_sandboxing_directive(&serialized), flags);
+    }
+    let tokens: Vec<String> = vec!["allow-top-navigation".to_owned(), "allow-top-navigation-by-user-activation".to_owned()];
+    assert_eq!(parse_a_sandboxing_directive(&tokens).to_tokens(), vec!["allow-top-navigation"]);
+}
+
+#[test]
+fn sandbox_flags_from_csp_list() {
+    use content_security_policy::sandboxing_directive::*;
+    let csp_list = CspList::parse("img-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    assert_eq!(csp_list.sandboxing_flags(), SandboxingFlagSet::empty());
+    let mut csp_list = CspList::parse("sandbox allow-scripts", PolicySource::Header, PolicyDisposition::Enforce);
+    csp_list.append(CspList::parse("sandbox", PolicySource::Header, PolicyDisposition::Report));
+    let flags = csp_list.sandboxing_flags();
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG));
+    assert!(!flags.contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+}
+
+#[test]
+fn sandbox_flags_from_several_enforced_policies() {
+    use content_security_policy::sandboxing_directive::*;
+    // Unlike HTML, which only uses the last `sandbox` directive, every one of them applies.
+    let mut csp_list = CspList::parse("sandbox", PolicySource::Header, PolicyDisposition::Enforce);
+    csp_list.append(CspList::parse("sandbox allow-scripts allow-same-origin", PolicySource::Header, PolicyDisposition::Enforce));
+    let flags = csp_list.sandboxing_flags();
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG));
+    let mut csp_list = CspList::parse("sandbox allow-scripts allow-forms", PolicySource::Header, PolicyDisposition::Enforce);
+    csp_list.append(CspList::parse("sandbox allow-scripts allow-popups", PolicySource::Header, PolicyDisposition::Enforce));
+    let flags = csp_list.sandboxing_flags();
+    assert!(!flags.contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG));
+    assert!(flags.contains(SandboxingFlagSet::SANDBOXED_AUXILIARY_NAVIGATION_BROWSING_CONTEXT_FLAG));
+}
+
+#[test]
+fn sandbox_flags_ignore_meta_policies() {
+    use content_security_policy::sandboxing_directive::*;
+    let csp_list = CspList::parse("sandbox", PolicySource::Meta, PolicyDisposition::Enforce);
+    assert_eq!(csp_list.sandboxing_flags(), SandboxingFlagSet::empty());
+    let mut csp_list = CspList::parse("sandbox allow-scripts", PolicySource::Header, PolicyDisposition::Enforce);
+    csp_list.append(CspList::parse("sandbox", PolicySource::Meta, PolicyDisposition::Enforce));
+    assert!(!csp_list.sandboxing_flags().contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+    assert!(csp_list.sandboxing_flags().contains(SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG));
+}
+
+#[test]
+fn sandbox_flags_combined_with_iframe_attribute() {
+    use content_security_policy::sandboxing_directive::*;
+    let attribute: Vec<String> = vec!["allow-scripts".to_owned(), "allow-same-origin".to_owned()];
+    let creation = determine_the_creation_sandboxing_flags(
+        SandboxingFlagSet::empty(),
+        Some(EmbedderSandboxingFlags {
+            iframe_sandboxing_flag_set: parse_a_sandboxing_directive(&attribute),
+            node_document_active_sandboxing_flag_set: SandboxingFlagSet::SANDBOXED_MODALS_FLAG,
+        }),
+    );
+    assert!(creation.contains(SandboxingFlagSet::SANDBOXED_MODALS_FLAG));
+    assert!(!creation.contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+    let csp_list = CspList::parse("sandbox allow-same-origin", PolicySource::Header, PolicyDisposition::Enforce);
+    let active = csp_list.active_sandboxing_flags(creation);
+    assert!(active.contains(SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG));
+    assert!(!active.contains(SandboxingFlagSet::SANDBOXED_ORIGIN_BROWSING_CONTEXT_FLAG));
+    let top_level = determine_the_creation_sandboxing_flags(SandboxingFlagSet::empty(), None);
+    assert_eq!(CspList(vec![]).active_sandboxing_flags(top_level), SandboxingFlagSet::empty());
+}
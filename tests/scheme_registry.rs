@@ -0,0 +1,120 @@
+extern crate content_security_policy;
+use content_security_policy::*;
+use content_security_policy::scheme_registry::{SchemeProperties, SchemeRegistry};
+#[macro_use]
+mod common;
+use common::{csp_list, request};
+
+fn registry() -> SchemeRegistry {
+    let mut registry = SchemeRegistry::default();
+    registry.register("app", SchemeProperties {
+        network: true,
+        secure: true,
+        default_port: Some(443),
+        upgrade_from: vec!["bundle".to_owned()],
+    });
+    registry.register("bundle", SchemeProperties {
+        network: false,
+        secure: false,
+        default_port: Some(8080),
+        upgrade_from: vec!["bundle-socket".to_owned()],
+    });
+    registry
+}
+
+fn app_origin() -> Origin {
+    Origin::Tuple("app".to_owned(), url::Host::Domain("shell".to_owned()), 443)
+}
+
+fn https_origin() -> Origin {
+    Url::parse("https://www.notriddle.com").unwrap().origin()
+}
+
+/// Checks rows of `(policy, url, origin, expected)` against script requests, using `registry()`.
+fn assert_rows(rows: Vec<(&str, &str, Origin, CheckResult)>) {
+    let options = RequestCheckOptions::default().scheme_registry(registry());
+    for (policy, url, origin, expected) in rows {
+        let request = Request { origin, ..request(url, Destination::Script) };
+        let (check_result, _) = csp_list(policy).should_request_be_blocked_with_options(&request, &options);
+        assert_eq!(check_result, expected, "{} loading {}", policy, url);
+    }
+}
+
+#[test]
+fn scheme_registry_default_schemes() {
+    let registry = SchemeRegistry::default();
+    assert!(registry.is_network("HTTP"));
+    assert!(!registry.is_network("ws"));
+    assert!(registry.is_secure("wss"));
+    assert_eq!(registry.default_port("https"), Some(443));
+    assert_eq!(registry.default_port("app"), None);
+    assert!(registry.does_scheme_match("http", "https"));
+    assert!(registry.does_scheme_match("ws", "https"));
+    assert!(!registry.does_scheme_match("https", "http"));
+    assert!(SchemeRegistry::empty().get("http").is_none());
+}
+
+#[test]
+fn scheme_registry_star_matches_network_schemes() {
+    assert_rows(vec![
+        ("script-src *", "app://shell/index.js", https_origin(), CheckResult::Allowed),
+        ("script-src *", "bundle://shell/index.js", https_origin(), CheckResult::Blocked),
+        ("script-src *", "unknown://shell/index.js", https_origin(), CheckResult::Blocked),
+    ]);
+}
+
+#[test]
+fn scheme_registry_self() {
+    assert_rows(vec![
+        ("script-src 'self'", "app://shell/index.js", app_origin(), CheckResult::Allowed),
+        ("script-src 'self'", "app://shell:443/index.js", app_origin(), CheckResult::Allowed),
+        ("script-src 'self'", "app://shell:444/index.js", app_origin(), CheckResult::Blocked),
+        ("script-src 'self'", "app://other/index.js", app_origin(), CheckResult::Blocked),
+        // `bundle` is not secure, so `'self'` does not match it from an `app` origin.
+        ("script-src 'self'", "bundle://shell/index.js", app_origin(), CheckResult::Blocked),
+    ]);
+}
+
+#[test]
+fn scheme_registry_self_on_insecure_origin() {
+    let bundle_origin = Origin::Tuple("bundle".to_owned(), url::Host::Domain("shell".to_owned()), 8080);
+    let http_origin = Url::parse("http://www.notriddle.com").unwrap().origin();
+    assert_rows(vec![
+        ("script-src 'self'", "bundle-socket://shell:8080/", bundle_origin.clone(), CheckResult::Allowed),
+        ("script-src 'self'", "app://shell/index.js", bundle_origin.clone(), CheckResult::Allowed),
+        ("script-src 'self'", "unknown://shell:8080/", bundle_origin.clone(), CheckResult::Blocked),
+        ("script-src 'self'", "ws://www.notriddle.com/", http_origin.clone(), CheckResult::Allowed),
+        ("script-src 'self'", "ftp://www.notriddle.com/", http_origin.clone(), CheckResult::Blocked),
+        ("script-src 'self'", "http://www.notriddle.com/", https_origin(), CheckResult::Blocked),
+    ]);
+}
+
+#[test]
+fn scheme_registry_defaults_to_builtin_schemes() {
+    let csp_list = CspList::parse("script-src *", PolicySource::Header, PolicyDisposition::Enforce);
+    let request = Request { origin: https_origin(), ..request("app://shell/index.js", Destination::Script) };
+    assert_eq!(csp_list.should_request_be_blocked(&request).0, CheckResult::Blocked);
+    assert_eq!(RequestCheckOptions::default().scheme_registry, RequestCheckOptions::default().scheme_registry(SchemeRegistry::default()).scheme_registry);
+}
+
+#[test]
+fn scheme_registry_upgrades_and_ports() {
+    assert_rows(vec![
+        ("script-src bundle:", "app://shell/index.js", app_origin(), CheckResult::Allowed),
+        ("script-src app:", "bundle://shell/index.js", app_origin(), CheckResult::Blocked),
+        ("script-src bundle://shell", "bundle://shell:8080/index.js", app_origin(), CheckResult::Allowed),
+        ("script-src bundle://shell:8080", "bundle://shell/index.js", app_origin(), CheckResult::Allowed),
+        ("script-src bundle://shell", "bundle://shell:8081/index.js", app_origin(), CheckResult::Blocked),
+        ("script-src app://shell:*", "app://shell:1234/index.js", app_origin(), CheckResult::Allowed),
+    ]);
+}
+
+#[test]
+fn scheme_registry_builtin_ports() {
+    assert_rows(vec![
+        ("script-src www.notriddle.com:8443", "https://www.notriddle.com:8443/a.js", https_origin(), CheckResult::Allowed),
+        ("script-src www.notriddle.com:443", "https://www.notriddle.com/a.js", https_origin(), CheckResult::Allowed),
+        ("script-src www.notriddle.com", "https://www.notriddle.com:8443/a.js", https_origin(), CheckResult::Blocked),
+        ("script-src ws:", "https://www.notriddle.com/a.js", https_origin(), CheckResult::Allowed),
+    ]);
+}
@@ -0,0 +1,88 @@
+#![cfg(feature = "serde")]
+extern crate content_security_policy;
+extern crate serde_json;
+use content_security_policy::*;
+#[macro_use]
+mod common;
+
+/// Recorded requests are replayed from JSON, so this format must not change.
+const REQUEST_JSON: &str = r#"{"url":"https://www.evil.com/script.js","origin":"https://www.notriddle.com","redirect_count":0,"destination":"Script","initiator":"None","nonce":"","integrity_metadata":"","parser_metadata":"ParserInserted"}"#;
+
+fn request() -> Request {
+    Request {
+        parser_metadata: ParserMetadata::ParserInserted,
+        ..common::request("https://www.evil.com/script.js", Destination::Script)
+    }
+}
+
+#[test]
+fn serde_request_format_is_stable() {
+    assert_eq!(serde_json::to_string(&request()).unwrap(), REQUEST_JSON);
+    let request: Request = serde_json::from_str(REQUEST_JSON).unwrap();
+    assert_eq!(request.origin, Url::parse("https://www.notriddle.com").unwrap().origin());
+    assert_eq!(request.parser_metadata, ParserMetadata::ParserInserted);
+}
+
+#[test]
+fn serde_opaque_origin() {
+    let mut request = request();
+    request.origin = Url::parse("data:text/html,hi").unwrap().origin();
+    let json = serde_json::to_string(&request).unwrap();
+    assert!(json.contains(r#""origin":"null""#));
+    let request: Request = serde_json::from_str(&json).unwrap();
+    assert!(!request.origin.is_tuple());
+}
+
+#[test]
+fn serde_replay_request_and_violation() {
+    let csp_list = CspList::parse("script-src 'self'", PolicySource::Header, PolicyDisposition::Enforce);
+    let csp_list: CspList = serde_json::from_str(&serde_json::to_string(&csp_list).unwrap()).unwrap();
+    let request: Request = serde_json::from_str(REQUEST_JSON).unwrap();
+    let (result, violations) = csp_list.should_request_be_blocked(&request);
+    assert_eq!(result, CheckResult::Blocked);
+    let json = serde_json::to_string(&violations).unwrap();
+    let violations: Vec<Violation> = serde_json::from_str(&json).unwrap();
+    assert_eq!(violations[0].effective_directive, "script-src-elem");
+    match violations[0].resource {
+        ViolationResource::Url(ref url) => assert_eq!(url.as_str(), "https://www.evil.com/script.js"),
+        ref resource => panic!("unexpected resource {:?}", resource),
+    }
+    let response = Response {
+        csp_list: csp_list.clone(),
+        url: request.url.clone(),
+        redirect_count: 0,
+    };
+    let response: Response = serde_json::from_str(&serde_json::to_string(&response).unwrap()).unwrap();
+    assert_eq!(response.url, request.url);
+}
+
+#[test]
+fn serde_element_and_sandboxing_flags() {
+    use content_security_policy::sandboxing_directive::SandboxingFlagSet;
+    let element = Element { nonce: Some("abc".into()) };
+    let element: Element = serde_json::from_str(&serde_json::to_string(&element).unwrap()).unwrap();
+    assert_eq!(element.nonce.as_ref().map(|n| &n[..]), Some("abc"));
+    let flags = SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG | SandboxingFlagSet::SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG;
+    assert_eq!(
+        serde_json::to_string(&flags).unwrap(),
+        r#"["SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG","SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG"]"#,
+    );
+    assert_eq!(serde_json::to_string(&SandboxingFlagSet::empty()).unwrap(), "[]");
+    assert!(serde_json::from_str::<SandboxingFlagSet>(r#"["SANDBOXED_TELEPORTATION_FLAG"]"#).is_err());
+    assert!(serde_json::from_str::<SandboxingFlagSet>("320").is_err());
+}
+
+#[test]
+fn serde_sandboxing_flags_fixture_round_trips() {
+    use content_security_policy::sandboxing_directive::{EmbedderSandboxingFlags, SandboxingFlagSet};
+    let fixture = include_str!("serde/embedder_sandboxing_flags.json");
+    let flags: EmbedderSandboxingFlags = serde_json::from_str(fixture).unwrap();
+    assert_eq!(flags, EmbedderSandboxingFlags {
+        iframe_sandboxing_flag_set: SandboxingFlagSet::SANDBOXED_FORMS_BROWSING_CONTEXT_FLAG |
+            SandboxingFlagSet::SANDBOXED_SCRIPTS_BROWSING_CONTEXT_FLAG,
+        node_document_active_sandboxing_flag_set: SandboxingFlagSet::empty(),
+    });
+    assert_eq!(serde_json::to_string_pretty(&flags).unwrap(), fixture.trim_end());
+    let all: SandboxingFlagSet = serde_json::from_str(&serde_json::to_string(&SandboxingFlagSet::all()).unwrap()).unwrap();
+    assert_eq!(all, SandboxingFlagSet::all());
+}
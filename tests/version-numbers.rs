@@ -1,8 +1,8 @@
-#[cfg(feature = "version_sync")]
+#[cfg(feature = "version-sync")]
 #[macro_use]
 extern crate version_sync;
 
-#[cfg(feature = "version_sync")]
+#[cfg(feature = "version-sync")]
 #[test]
 fn test_readme_deps() {
     assert_markdown_deps_updated!("README.md");